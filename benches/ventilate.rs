@@ -0,0 +1,167 @@
+//! Ventilation of a synthetic ten year history with a few hundred patterns.
+//!
+//! Run with `cargo bench --bench ventilate`.
+
+use chrono::{Days, NaiveDate};
+use criterion::{Criterion, criterion_group, criterion_main};
+use my_bank_statements::parser::model::{Operation, Releve, Solde, SoldeType};
+use my_bank_statements::ventilation::compiled::CompiledSpec;
+use my_bank_statements::ventilation::model::{Assignment, VentilationSpec};
+use my_bank_statements::ventilation::transfers::{OwnAccount, find_transfers};
+use my_bank_statements::ventilation::ventilate::{ventilate, ventilate_compiled};
+use std::hint::black_box;
+
+const YEARS: u32 = 10;
+const OPERATIONS_PER_MONTH: u32 = 120;
+const MERCHANTS: usize = 300;
+
+/// 30 categories of 10 merchants each, one pattern per merchant
+fn spec() -> VentilationSpec {
+    let assignments = (0..MERCHANTS / 10)
+        .map(|category| Assignment {
+            name: format!("Categorie {category}"),
+            patterns: (0..10)
+                .map(|m| format!("MARCHAND {:03}\\b", category * 10 + m).into())
+                .collect(),
+            ..Default::default()
+        })
+        .collect();
+    VentilationSpec {
+        name: "Bench".to_string(),
+        assignments,
+        ..Default::default()
+    }
+}
+
+/// One releve a month, some operations matching no pattern
+fn releves() -> Vec<Releve> {
+    let start = NaiveDate::from_ymd_opt(2015, 1, 1).unwrap();
+    let mut releves = Vec::new();
+    for month in 0..YEARS * 12 {
+        let first = start
+            .checked_add_months(chrono::Months::new(month))
+            .unwrap();
+        let operations: Vec<Operation> = (0..OPERATIONS_PER_MONTH)
+            .map(|i| {
+                let date = first
+                    .checked_add_days(Days::new(u64::from(i % 28)))
+                    .unwrap();
+                let merchant = (month * OPERATIONS_PER_MONTH + i) as usize % (MERCHANTS + 20);
+                Operation {
+                    date,
+                    nature_des_operations: format!(
+                        "FACTURE CARTE DU {} MARCHAND {merchant:03} PARIS CARTE 4974XXXXXXXX1234",
+                        date.format("%d%m%y")
+                    ),
+                    valeur: date,
+                    montant: 1000 + i64::from(i),
+                    montant_type: SoldeType::Debit,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let debit: i64 = operations.iter().map(|op| op.montant).sum();
+        releves.push(Releve {
+            date_du_releve: first.checked_add_days(Days::new(27)).unwrap(),
+            solde_ouverture: Solde {
+                solde_type: SoldeType::Credit,
+                montant: debit,
+            },
+            solde_cloture: Solde {
+                solde_type: SoldeType::Credit,
+                montant: 0,
+            },
+            total_des_operations_debit: debit,
+            total_des_operations_credit: 0,
+            check_debit: debit,
+            check_credit: 0,
+            operations,
+            account: None,
+        });
+    }
+    releves
+}
+
+/// The releves of `releves` as those of a current account, and those of a
+/// savings account receiving every fourth of its debits the next day
+fn two_accounts() -> Vec<Releve> {
+    let courant: Vec<Releve> = releves()
+        .into_iter()
+        .map(|releve| Releve {
+            account: Some("courant".to_string()),
+            ..releve
+        })
+        .collect();
+    let livret = courant.iter().map(|releve| {
+        let operations: Vec<Operation> = releve
+            .operations
+            .iter()
+            .step_by(4)
+            .map(|op| Operation {
+                date: op.date.succ_opt().unwrap(),
+                valeur: op.date.succ_opt().unwrap(),
+                montant_type: SoldeType::Credit,
+                ..op.clone()
+            })
+            .collect();
+        let credit: i64 = operations.iter().map(|op| op.montant).sum();
+        Releve {
+            solde_ouverture: Solde {
+                solde_type: SoldeType::Credit,
+                montant: 0,
+            },
+            solde_cloture: Solde {
+                solde_type: SoldeType::Credit,
+                montant: credit,
+            },
+            total_des_operations_debit: 0,
+            total_des_operations_credit: credit,
+            check_debit: 0,
+            check_credit: credit,
+            operations,
+            account: Some("livret".to_string()),
+            ..releve.clone()
+        }
+    });
+    let livret: Vec<Releve> = livret.collect();
+    courant.into_iter().chain(livret).collect()
+}
+
+fn bench_ventilate(c: &mut Criterion) {
+    let spec = spec();
+    let releves = releves();
+    let compiled = CompiledSpec::new(spec.clone()).unwrap();
+
+    let mut group = c.benchmark_group("ventilate");
+    group.sample_size(10);
+    group.bench_function("ten_years", |b| {
+        b.iter(|| ventilate(black_box(spec.clone()), black_box(&releves)).unwrap())
+    });
+    group.bench_function("ten_years_precompiled", |b| {
+        b.iter(|| ventilate_compiled(black_box(&compiled), black_box(&releves)).unwrap())
+    });
+    group.bench_function("compile_spec", |b| {
+        b.iter(|| CompiledSpec::new(black_box(spec.clone())).unwrap())
+    });
+
+    let mut spec = spec;
+    spec.transfers.accounts = ["courant", "livret"]
+        .map(|name| OwnAccount {
+            name: name.to_string(),
+            iban: None,
+            labels: vec![],
+        })
+        .to_vec();
+    let releves = two_accounts();
+    let compiled = CompiledSpec::new(spec.clone()).unwrap();
+    group.bench_function("find_transfers_two_accounts", |b| {
+        b.iter(|| find_transfers(black_box(&releves), black_box(&spec.transfers)))
+    });
+    group.bench_function("ten_years_two_accounts", |b| {
+        b.iter(|| ventilate_compiled(black_box(&compiled), black_box(&releves)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_ventilate);
+criterion_main!(benches);
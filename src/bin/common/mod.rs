@@ -0,0 +1,41 @@
+//! Reading of the command line options shared by the binaries
+
+use serde::de::DeserializeOwned;
+use std::fs;
+
+/// Remove `--name value` from `args` and return the value, exiting with
+/// `usage` when the value is missing.
+pub fn option(args: &mut Vec<String>, name: &str, usage: &str) -> Option<String> {
+    let i = args.iter().position(|a| a == name)?;
+    if i + 1 >= args.len() {
+        eprintln!("{usage}");
+        std::process::exit(1);
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Some(value)
+}
+
+/// Read and parse the YAML file at `path`, exiting on error; `what` names
+/// the file in the messages.
+pub fn load_yaml<T: DeserializeOwned>(path: &str, what: &str) -> T {
+    let content = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {what} {path}: {e}");
+        std::process::exit(1);
+    });
+    serde_yaml::from_str(&content).unwrap_or_else(|e| {
+        eprintln!("Failed to parse {what}: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Remove `--name file.yml` from `args` and read the YAML file, `None` when
+/// the option is not given.
+pub fn yaml_option<T: DeserializeOwned>(
+    args: &mut Vec<String>,
+    name: &str,
+    what: &str,
+    usage: &str,
+) -> Option<T> {
+    option(args, name, usage).map(|path| load_yaml(&path, what))
+}
@@ -1,5 +1,7 @@
 use my_bank_statements::Releve;
-use my_bank_statements::ventilation::model::{Assignment, VentilationSpec};
+use my_bank_statements::parser::model::euros;
+use my_bank_statements::ventilation::model::{Assignment, Pattern, VentilationSpec};
+use my_bank_statements::ventilation::overrides::{Override, duplicate_keys, operation_key};
 use my_bank_statements::ventilation::ventilate::ventilate;
 use std::collections::HashSet;
 use std::fs;
@@ -368,29 +370,36 @@ fn extract_pattern_from_operation(operation: &str) -> String {
     words.join(" ")
 }
 
+const USAGE: &str = "Usage: my-bank-statements-add-patterns [--overrides overrides.yml] <releves.yml> <ventilation_spec.yml> [output.yml]";
+
 fn main() {
-    let releves_path = match std::env::args().nth(1) {
-        Some(p) => p,
-        None => {
-            eprintln!(
-                "Usage: my-bank-statements-add-patterns <releves.yml> <ventilation_spec.yml> [output.yml]"
-            );
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Operations assigned one by one are written there
+    let overrides_path = match args.iter().position(|a| a == "--overrides") {
+        Some(i) if i + 1 < args.len() => {
+            let path = args.remove(i + 1);
+            args.remove(i);
+            path
+        }
+        Some(_) => {
+            eprintln!("{USAGE}");
             std::process::exit(1);
         }
+        None => "overrides.yml".to_string(),
     };
 
-    let spec_path = match std::env::args().nth(2) {
-        Some(p) => p,
-        None => {
-            eprintln!(
-                "Usage: my-bank-statements-add-patterns <releves.yml> <ventilation_spec.yml> [output.yml]"
-            );
+    let (releves_path, spec_path) = match args.as_slice() {
+        [releves, spec, ..] => (releves.clone(), spec.clone()),
+        _ => {
+            eprintln!("{USAGE}");
             std::process::exit(1);
         }
     };
 
-    let output_path = std::env::args()
-        .nth(3)
+    let output_path = args
+        .get(2)
+        .cloned()
         .unwrap_or_else(|| "ventilation_spec_updated.yml".to_string());
 
     // Read releves
@@ -415,8 +424,23 @@ fn main() {
         std::process::exit(1);
     });
 
+    let mut overrides: Vec<Override> = match fs::read_to_string(&overrides_path) {
+        Ok(content) => serde_yaml::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("Failed to parse overrides {overrides_path}: {e}");
+            std::process::exit(1);
+        }),
+        // The file is created by the first override
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            eprintln!("Failed to read overrides {overrides_path}: {e}");
+            std::process::exit(1);
+        }
+    };
+
     // Run ventilation
-    let result = match ventilate(spec.clone(), &releves) {
+    let mut ventilated_spec = spec.clone();
+    ventilated_spec.overrides.extend(overrides.iter().cloned());
+    let result = match ventilate(ventilated_spec, &releves) {
         Ok(r) => r,
         Err(e) => {
             eprintln!("Ventilation error: {e}");
@@ -424,19 +448,20 @@ fn main() {
         }
     };
 
-    if result.not_assigned_operations.is_empty() {
+    if result.debit.not_assigned_operations.is_empty() {
         println!("No unassigned operations found!");
         return;
     }
 
     println!(
         "Found {} unassigned operations.\n",
-        result.not_assigned_operations.len()
+        result.debit.not_assigned_operations.len()
     );
 
     // Collect unique operation descriptions
     let mut seen: HashSet<String> = HashSet::new();
     let unique_operations: Vec<_> = result
+        .debit
         .not_assigned_operations
         .iter()
         .filter(|op| seen.insert(op.nature_des_operations.clone()))
@@ -449,8 +474,11 @@ fn main() {
 
     // Get suggestions
     let suggestions = get_suggestions();
+    // Operations an override cannot tell apart
+    let duplicate_keys = duplicate_keys(&releves);
 
     let mut changes_made = false;
+    let mut overrides_changed = false;
     let mut auto_mode = false;
     let mut skip_all = false;
 
@@ -468,7 +496,7 @@ fn main() {
 
         println!("----------------------------------------");
         println!("Operation: {}", op.nature_des_operations);
-        println!("Amount: {:.2} EUR", op.montant as f64 / 100.0);
+        println!("Amount: {} EUR", euros(op.montant));
         println!("Date: {}", op.date);
 
         if let Some((ref cat, _)) = suggestion {
@@ -489,6 +517,7 @@ fn main() {
             println!("  {}. {}", i + 1, name);
         }
         println!("  n. Create new category");
+        println!("  o. Assign just this operation");
         println!();
 
         print!("Choice: ");
@@ -525,16 +554,17 @@ fn main() {
                     let new_name = new_cat.to_string();
                     spec.assignments.push(Assignment {
                         name: new_name.clone(),
-                        patterns: vec![suggested_pattern.clone()],
-                        ignore: false,
+                        patterns: vec![suggested_pattern.as_str().into()],
+                        ..Default::default()
                     });
                     println!("Created category '{new_name}' with pattern '{suggested_pattern}'.\n");
                     changes_made = true;
                 } else {
                     // Find the category and add the pattern
                     if let Some(assignment) = spec.assignments.iter_mut().find(|a| a.name == cat) {
-                        if !assignment.patterns.contains(&suggested_pattern) {
-                            assignment.patterns.push(suggested_pattern.clone());
+                        let pattern = Pattern::from(suggested_pattern.as_str());
+                        if !assignment.patterns.contains(&pattern) {
+                            assignment.patterns.push(pattern);
                             println!("Added pattern '{suggested_pattern}' to category '{cat}'.\n");
                             changes_made = true;
                         } else {
@@ -578,11 +608,15 @@ fn main() {
             } else {
                 pattern.to_string()
             };
+            if let Err(e) = regex::Regex::new(&pattern) {
+                println!("Invalid regex, skipped: {e}\n");
+                continue;
+            }
 
             spec.assignments.push(Assignment {
                 name: new_name.clone(),
-                patterns: vec![pattern.clone()],
-                ignore: false,
+                patterns: vec![pattern.as_str().into()],
+                ..Default::default()
             });
 
             println!("Created category '{new_name}' with pattern '{pattern}'.\n");
@@ -590,6 +624,31 @@ fn main() {
             continue;
         }
 
+        // Handle override of this operation only
+        if input == "o" || input == "O" {
+            if op.id.is_none() && duplicate_keys.contains(&operation_key(op)) {
+                println!(
+                    "Another operation has the same date, amount and description: an override would assign both, skipped.\n"
+                );
+                continue;
+            }
+            print!("Category number: ");
+            io::stdout().flush().unwrap();
+
+            let mut choice = String::new();
+            io::stdin().read_line(&mut choice).unwrap();
+            match choice.trim().parse::<usize>() {
+                Ok(choice) if choice > 0 && choice <= category_names.len() => {
+                    let category_name = &category_names[choice - 1];
+                    overrides.push(Override::new(op, category_name));
+                    println!("Assigned this operation to category '{category_name}'.\n");
+                    overrides_changed = true;
+                }
+                _ => println!("Invalid choice, skipped.\n"),
+            }
+            continue;
+        }
+
         // Handle number choice
         if let Ok(choice) = input.parse::<usize>() {
             if choice > 0 && choice <= category_names.len() {
@@ -608,10 +667,14 @@ fn main() {
                 } else {
                     pattern.to_string()
                 };
+                if let Err(e) = regex::Regex::new(&pattern) {
+                    println!("Invalid regex, skipped: {e}\n");
+                    continue;
+                }
 
                 spec.assignments[category_idx]
                     .patterns
-                    .push(pattern.clone());
+                    .push(pattern.as_str().into());
 
                 println!("Added pattern '{pattern}' to category '{category_name}'.\n");
                 changes_made = true;
@@ -628,7 +691,14 @@ fn main() {
         let yaml = serde_yaml::to_string(&spec).expect("Failed to serialize spec to YAML");
         fs::write(&output_path, &yaml).expect("Failed to write updated spec");
         println!("\nWrote updated spec to {output_path}");
-    } else {
+    }
+    if overrides_changed {
+        let yaml =
+            serde_yaml::to_string(&overrides).expect("Failed to serialize overrides to YAML");
+        fs::write(&overrides_path, &yaml).expect("Failed to write overrides");
+        println!("\nWrote overrides to {overrides_path}");
+    }
+    if !changes_made && !overrides_changed {
         println!("\nNo changes made.");
     }
 }
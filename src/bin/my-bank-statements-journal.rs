@@ -0,0 +1,75 @@
+use my_bank_statements::Releve;
+use my_bank_statements::export::{JournalFormat, JournalOptions, to_journal};
+use my_bank_statements::ventilation::model::VentilationSpec;
+use my_bank_statements::ventilation::overrides::Override;
+use std::fs;
+
+mod common;
+use common::yaml_option;
+
+const USAGE: &str = "Usage: my-bank-statements-journal [--options journal.yml] [--overrides overrides.yml] <releves.yml> <ventilation_spec.yml> <ledger|hledger|beancount> [output]";
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    let options: JournalOptions =
+        yaml_option(&mut args, "--options", "journal options", USAGE).unwrap_or_default();
+    let overrides: Vec<Override> =
+        yaml_option(&mut args, "--overrides", "overrides", USAGE).unwrap_or_default();
+
+    let (releves_path, spec_path, format) = match args.as_slice() {
+        [releves, spec, format, ..] => (releves, spec, format),
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    let format: JournalFormat = format.parse().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+
+    let output_path = args.get(3).cloned().unwrap_or_else(|| {
+        match format {
+            JournalFormat::Ledger => "journal.ledger",
+            JournalFormat::Hledger => "journal.journal",
+            JournalFormat::Beancount => "journal.beancount",
+        }
+        .to_string()
+    });
+
+    // Read releves
+    let releves_content = fs::read_to_string(releves_path).unwrap_or_else(|e| {
+        eprintln!("Failed to read releves file {releves_path}: {e}");
+        std::process::exit(1);
+    });
+
+    let releves: Vec<Releve> = serde_yaml::from_str(&releves_content).unwrap_or_else(|e| {
+        eprintln!("Failed to parse releves: {e}");
+        std::process::exit(1);
+    });
+
+    // Read ventilation spec
+    let spec_content = fs::read_to_string(spec_path).unwrap_or_else(|e| {
+        eprintln!("Failed to read ventilation spec {spec_path}: {e}");
+        std::process::exit(1);
+    });
+
+    let mut spec: VentilationSpec = serde_yaml::from_str(&spec_content).unwrap_or_else(|e| {
+        eprintln!("Failed to parse ventilation spec: {e}");
+        std::process::exit(1);
+    });
+    spec.overrides.extend(overrides);
+
+    match to_journal(&releves, &spec, format, &options) {
+        Ok(journal) => {
+            fs::write(&output_path, &journal).expect("Failed to write journal");
+            println!("Wrote {} releves to {output_path}", releves.len());
+        }
+        Err(e) => {
+            eprintln!("Journal error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,29 +1,203 @@
-use my_bank_statements::{Releve, parse_pdf};
+use my_bank_statements::export::to_camt053;
+use my_bank_statements::import::{
+    CsvFormat, QifFormat, import_camt053, import_csv, import_ofx, import_qif,
+};
+use my_bank_statements::store::{ImportOutcome, Store};
+use my_bank_statements::{ParserProfile, Releve, check_continuity, parse_pdf_with_profile};
+use serde::de::DeserializeOwned;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "Usage: my-bank-statements-parser [--profile profile.yml] [--csv-format format.yml] [--qif-date-format FORMAT] [--qif-decimal-comma] [--iban IBAN] [--account name] <file_or_directory> [output.yml|output.xml]
+       my-bank-statements-parser [--profile profile.yml] [--csv-format format.yml] --check <directory_or_releves.yml>
+       my-bank-statements-parser [--profile profile.yml] [--csv-format format.yml] --store <db.sqlite> --account <name> <file_or_directory>
+
+Files can be PDF statements, CSV, OFX/QFX and QIF exports, or camt.053 (.xml).
+QIF dates are read with --qif-date-format (default %d/%m/%Y), their amounts with a decimal point
+unless --qif-decimal-comma is given.
+An output ending in .xml is written as camt.053 for the account --iban.";
+
+/// Extensions of the files read from a directory
+const EXTENSIONS: &[&str] = &["pdf", "csv", "ofx", "qfx", "qif", "xml"];
+
+/// How to read the input files and write the output
+struct Options {
+    profile: ParserProfile,
+    csv: CsvFormat,
+    qif: QifFormat,
+    iban: Option<String>,
+    /// Account written in the releves
+    account: Option<String>,
+}
 
 fn main() {
-    let path = match std::env::args().nth(1) {
-        Some(p) => p,
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    let options = Options {
+        profile: option(&mut args, "--profile")
+            .map_or_else(ParserProfile::default, |p| load_yaml(&p, "profile")),
+        csv: option(&mut args, "--csv-format")
+            .map_or_else(CsvFormat::default, |p| load_yaml(&p, "CSV format")),
+        qif: QifFormat {
+            date_format: option(&mut args, "--qif-date-format")
+                .unwrap_or_else(|| QifFormat::default().date_format),
+            decimal_comma: flag(&mut args, "--qif-decimal-comma"),
+        },
+        iban: option(&mut args, "--iban"),
+        account: option(&mut args, "--account"),
+    };
+    let store = option(&mut args, "--store");
+
+    let path = match args.first() {
+        Some(p) => p.clone(),
         None => {
-            eprintln!("Usage: my-bank-statements-parser <pdf_file_or_directory> [output.yml]");
+            eprintln!("{USAGE}");
             std::process::exit(1);
         }
     };
 
-    let output_path = std::env::args().nth(2);
+    if path == "--check" {
+        let Some(path) = args.get(1) else {
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        };
+        check(Path::new(path), &options);
+        return;
+    }
+
+    let output_path = args.get(1);
     let input_path = Path::new(&path);
 
+    if let Some(db) = store {
+        let Some(account) = options.account.clone() else {
+            eprintln!("--account is required with --store");
+            std::process::exit(1);
+        };
+        store_releves(input_path, &db, &account, &options);
+        return;
+    }
+
     if input_path.is_dir() {
-        process_directory(input_path, output_path.as_deref());
+        process_directory(input_path, output_path.map(|p| p.as_str()), &options);
     } else {
-        process_single_file(input_path, output_path.as_deref());
+        process_single_file(input_path, output_path.map(|p| p.as_str()), &options);
     }
 }
 
-fn process_directory(dir: &Path, output_path: Option<&str>) {
-    let mut releves: Vec<Releve> = Vec::new();
+/// Remove `--name value` from `args` and return the value.
+fn option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    match args.iter().position(|a| a == name) {
+        Some(i) if i + 1 < args.len() => {
+            let value = args.remove(i + 1);
+            args.remove(i);
+            Some(value)
+        }
+        Some(_) => {
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+        None => None,
+    }
+}
+
+/// Remove `--name` from `args` and tell whether it was there.
+fn flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|a| a == name) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+fn load_yaml<T: DeserializeOwned>(path: &str, what: &str) -> T {
+    let content = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {what} {path}: {e}");
+        std::process::exit(1);
+    });
+    serde_yaml::from_str(&content).unwrap_or_else(|e| {
+        eprintln!("Failed to parse {what}: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Parse a PDF statement or import an export, depending on the extension.
+fn read_file(path: &Path, options: &Options) -> Result<Vec<Releve>, String> {
+    let name = path.to_str().unwrap();
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+    let imported = match extension.as_deref() {
+        Some("csv") => import_csv(name, &options.csv).map(|r| vec![r]),
+        Some("ofx" | "qfx") => import_ofx(name).map(|r| vec![r]),
+        Some("qif") => import_qif(name, &options.qif).map(|r| vec![r]),
+        Some("xml") => import_camt053(name),
+        _ => {
+            return parse_pdf_with_profile(name, &options.profile)
+                .map(|r| vec![r])
+                .map_err(|e| e.to_string());
+        }
+    };
+    imported.map_err(|e| format!("{name}: {e}"))
+}
+
+/// Read a file and tag its releves with the `--account` option.
+fn parse_file(path: &Path, options: &Options) -> Result<Vec<Releve>, String> {
+    let mut releves = read_file(path, options)?;
+    if let Some(account) = &options.account {
+        for releve in &mut releves {
+            releve.account = Some(account.clone());
+        }
+    }
+    Ok(releves)
+}
+
+fn check(input_path: &Path, options: &Options) {
+    let releves: Vec<Releve> = if input_path.is_dir() {
+        parse_directory(input_path, options)
+    } else {
+        let content = fs::read_to_string(input_path).unwrap_or_else(|e| {
+            eprintln!("Failed to read releves file {}: {e}", input_path.display());
+            std::process::exit(1);
+        });
+        serde_yaml::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("Failed to parse releves: {e}");
+            std::process::exit(1);
+        })
+    };
+
+    let issues = check_continuity(&releves);
+    if issues.is_empty() {
+        println!("{} releves, no continuity issue found", releves.len());
+        return;
+    }
 
+    for issue in &issues {
+        println!("{issue}");
+    }
+    eprintln!(
+        "{} continuity issues in {} releves",
+        issues.len(),
+        releves.len()
+    );
+    std::process::exit(1);
+}
+
+fn process_directory(dir: &Path, output_path: Option<&str>, options: &Options) {
+    let releves = parse_directory(dir, options);
+
+    let out = output_path
+        .map(|p| Path::new(p).to_path_buf())
+        .unwrap_or_else(|| dir.join("releves.yml"));
+
+    write_releves(&out, &releves, options);
+
+    println!("Wrote {} releves to {}", releves.len(), out.display());
+}
+
+/// The statements and exports of `dir`, by file name.
+fn statement_files(dir: &Path) -> Vec<PathBuf> {
     let entries = match fs::read_dir(dir) {
         Ok(e) => e,
         Err(e) => {
@@ -32,11 +206,80 @@ fn process_directory(dir: &Path, output_path: Option<&str>) {
         }
     };
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.extension().map(|e| e.to_ascii_lowercase()) == Some("pdf".into()) {
-            match parse_pdf(path.to_str().unwrap()) {
-                Ok(releve) => {
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .map(|e| e.to_string_lossy().to_ascii_lowercase())
+                .is_some_and(|e| EXTENSIONS.contains(&e.as_str()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Import the releves of a file, or of all the files of a directory, into the store.
+fn store_releves(input_path: &Path, db: &str, account: &str, options: &Options) {
+    let mut store = Store::open(db).unwrap_or_else(|e| {
+        eprintln!("Failed to open store {db}: {e}");
+        std::process::exit(1);
+    });
+    let files = if input_path.is_dir() {
+        statement_files(input_path)
+    } else {
+        vec![input_path.to_path_buf()]
+    };
+
+    let mut conflicts = 0;
+    for path in files {
+        let source = path.file_name().unwrap().to_string_lossy().to_string();
+        let releves = match parse_file(&path, options) {
+            Ok(releves) => releves,
+            Err(e) => {
+                eprintln!("Error parsing: {e}");
+                continue;
+            }
+        };
+        for releve in releves {
+            let date = releve.date_du_releve;
+            match store.import_releve(account, &releve, &source) {
+                Ok(ImportOutcome::Inserted {
+                    operations,
+                    skipped,
+                }) => println!(
+                    "Imported {operations} operations (date: {date}) from {source}, {skipped} already in the store"
+                ),
+                Ok(ImportOutcome::Unchanged) => {
+                    println!("Already imported (date: {date}) from {source}")
+                }
+                Ok(ImportOutcome::Conflict { .. }) => {
+                    conflicts += 1;
+                    eprintln!(
+                        "Conflict: {source} differs from the releve of {date} already in the store"
+                    )
+                }
+                Err(e) => {
+                    eprintln!("Failed to import {source}: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    if conflicts > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Parse all the statements and exports of `dir`, sorted by date du releve.
+fn parse_directory(dir: &Path, options: &Options) -> Vec<Releve> {
+    let mut releves: Vec<Releve> = Vec::new();
+
+    for path in statement_files(dir) {
+        match parse_file(&path, options) {
+            Ok(parsed) => {
+                for releve in parsed {
                     println!(
                         "Parsed {} operations (date: {}) from {}",
                         releve.operations.len(),
@@ -45,45 +288,55 @@ fn process_directory(dir: &Path, output_path: Option<&str>) {
                     );
                     releves.push(releve);
                 }
-                Err(e) => eprintln!("Error parsing {}: {e}", path.display()),
             }
+            Err(e) => eprintln!("Error parsing: {e}"),
         }
     }
 
     if releves.is_empty() {
-        eprintln!("No PDF files found in directory");
+        eprintln!("No statement files found in directory");
         std::process::exit(1);
     }
 
     releves.sort_by_key(|r| r.date_du_releve);
+    releves
+}
 
-    let out = output_path
-        .map(|p| Path::new(p).to_path_buf())
-        .unwrap_or_else(|| dir.join("releves.yml"));
-
-    let yaml = serde_yaml::to_string(&releves).expect("Failed to serialize to YAML");
-    fs::write(&out, &yaml).expect("Failed to write YAML file");
-
-    println!("Wrote {} releves to {}", releves.len(), out.display());
+/// Write camt.053 if `out` ends with `.xml`, YAML otherwise.
+fn write_releves(out: &Path, releves: &[Releve], options: &Options) {
+    let is_xml = out
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("xml"));
+    let content = if is_xml {
+        let Some(iban) = &options.iban else {
+            eprintln!("--iban is required to write camt.053");
+            std::process::exit(1);
+        };
+        to_camt053(releves, iban)
+    } else {
+        serde_yaml::to_string(releves).expect("Failed to serialize to YAML")
+    };
+    fs::write(out, &content).expect("Failed to write output file");
 }
 
-fn process_single_file(input_path: &Path, output_path: Option<&str>) {
-    match parse_pdf(input_path.to_str().unwrap()) {
-        Ok(releve) => {
+fn process_single_file(input_path: &Path, output_path: Option<&str>, options: &Options) {
+    match parse_file(input_path, options) {
+        Ok(releves) => {
             let out = output_path.map(|p| p.to_string()).unwrap_or_else(|| {
                 let stem = input_path.file_stem().unwrap().to_str().unwrap();
                 format!("{stem}.yml")
             });
 
-            let yaml = serde_yaml::to_string(&releve).expect("Failed to serialize to YAML");
-            fs::write(&out, &yaml).expect("Failed to write YAML file");
+            write_releves(Path::new(&out), &releves, options);
 
-            println!(
-                "Parsed {} operations (date: {}) -> {out}",
-                releve.operations.len(),
-                releve.date_du_releve,
-            );
+            for releve in &releves {
+                println!(
+                    "Parsed {} operations (date: {}) -> {out}",
+                    releve.operations.len(),
+                    releve.date_du_releve,
+                );
+            }
         }
-        Err(e) => eprintln!("{e}"),
+        Err(e) => eprintln!("Error parsing: {e}"),
     }
 }
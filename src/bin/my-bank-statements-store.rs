@@ -0,0 +1,109 @@
+use chrono::NaiveDate;
+use my_bank_statements::store::Store;
+use std::fs;
+
+const USAGE: &str = "Usage: my-bank-statements-store <db.sqlite> releves [--account name] [output.yml]
+       my-bank-statements-store <db.sqlite> operations [--account name] [--from YYYY-MM-DD] [--to YYYY-MM-DD] [output.yml]
+       my-bank-statements-store <db.sqlite> log
+       my-bank-statements-store <db.sqlite> remove <account> <YYYY-MM-DD>
+
+Statements are imported with: my-bank-statements-parser --store <db.sqlite> --account <name> <file_or_directory>";
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(1);
+}
+
+/// Remove `--name value` from `args` and return the value.
+fn option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let i = args.iter().position(|a| a == name)?;
+    if i + 1 >= args.len() {
+        usage();
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Some(value)
+}
+
+fn parse_date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap_or_else(|_| {
+        eprintln!("Bad date {s}, expected YYYY-MM-DD");
+        std::process::exit(1);
+    })
+}
+
+fn write_yaml<T: serde::Serialize>(value: &T, output_path: Option<&String>) {
+    let yaml = serde_yaml::to_string(value).expect("Failed to serialize to YAML");
+    match output_path {
+        Some(path) => {
+            fs::write(path, &yaml).expect("Failed to write YAML file");
+            println!("Wrote {path}");
+        }
+        None => print!("{yaml}"),
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let account = option(&mut args, "--account");
+    let from = option(&mut args, "--from").map(|d| parse_date(&d));
+    let to = option(&mut args, "--to").map(|d| parse_date(&d));
+
+    let (db, command) = match args.as_slice() {
+        [db, command, ..] => (db.clone(), command.clone()),
+        _ => usage(),
+    };
+
+    let mut store = Store::open(&db).unwrap_or_else(|e| {
+        eprintln!("Failed to open store {db}: {e}");
+        std::process::exit(1);
+    });
+    let fail = |e: my_bank_statements::store::StoreError| -> ! {
+        eprintln!("Store error: {e}");
+        std::process::exit(1);
+    };
+
+    match command.as_str() {
+        "releves" => {
+            let releves = store
+                .releves(account.as_deref())
+                .unwrap_or_else(|e| fail(e));
+            write_yaml(&releves, args.get(2));
+        }
+        "operations" => {
+            let operations = store
+                .operations(account.as_deref(), from, to)
+                .unwrap_or_else(|e| fail(e));
+            write_yaml(&operations, args.get(2));
+        }
+        "log" => {
+            for entry in store.import_log().unwrap_or_else(|e| fail(e)) {
+                println!(
+                    "{} {} {} {} {} ({} operations)",
+                    entry.imported_at,
+                    entry.account,
+                    entry.date_du_releve,
+                    entry.source,
+                    entry.outcome,
+                    entry.operations
+                );
+            }
+        }
+        "remove" => {
+            let (Some(account), Some(date)) = (args.get(2), args.get(3)) else {
+                usage();
+            };
+            let date = parse_date(date);
+            if store
+                .remove_releve(account, date)
+                .unwrap_or_else(|e| fail(e))
+            {
+                println!("Removed the releve of {date} of {account}");
+            } else {
+                eprintln!("No releve of {date} for {account}");
+                std::process::exit(1);
+            }
+        }
+        _ => usage(),
+    }
+}
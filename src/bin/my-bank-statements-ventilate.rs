@@ -1,27 +1,156 @@
+use indexmap::IndexMap;
 use my_bank_statements::Releve;
-use my_bank_statements::ventilation::model::VentilationSpec;
+use my_bank_statements::parser::model::euros;
+use my_bank_statements::ventilation::compiled::CompiledSpec;
+use my_bank_statements::ventilation::examples::check_examples;
+use my_bank_statements::ventilation::explain::{OperationQuery, explain, find_operations};
+use my_bank_statements::ventilation::lint::{Coverage, LintReport, lint};
+use my_bank_statements::ventilation::model::{
+    AmbiguousMatch, Assignment, Breakdown, Resolution, VentilationSpec, truncate_category,
+};
+use my_bank_statements::ventilation::overrides::Override;
+use my_bank_statements::ventilation::period::{
+    DateField, Period, PeriodMatrix, ventilate_by_period,
+};
+use my_bank_statements::ventilation::transfers::{find_transfers, transfer_at};
 use my_bank_statements::ventilation::ventilate::ventilate;
 use std::fs;
 
+mod common;
+use common::{load_yaml, option, yaml_option};
+
+const USAGE: &str = "Usage: my-bank-statements-ventilate [--period month|quarter|year] [--date date|valeur] [--depth n] [--strict] [--overrides overrides.yml] <releves.yml> <ventilation_spec.yml> [output.yml]
+       my-bank-statements-ventilate --validate <ventilation_spec.yml>
+       my-bank-statements-ventilate --check <ventilation_spec.yml>
+       my-bank-statements-ventilate --lint [--overrides overrides.yml] <releves.yml> <ventilation_spec.yml>
+       my-bank-statements-ventilate --explain <id|date:montant|description> [--overrides overrides.yml] <releves.yml> <ventilation_spec.yml>";
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(1);
+}
+
+/// Remove `--name` from `args` and tell whether it was there.
+fn flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|a| a == name) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Read and parse a ventilation spec, exiting on error.
+fn load_spec(spec_path: &str) -> VentilationSpec {
+    load_yaml(spec_path, "ventilation spec")
+}
+
+/// Report every invalid regex of the spec, exiting with status 1 if there is any.
+fn validate(spec_path: &str) {
+    let spec = load_spec(spec_path);
+    let invalid = spec.invalid_patterns();
+    let invalid_splits = spec.invalid_splits();
+    let count: usize = spec
+        .assignments
+        .iter()
+        .chain(&spec.income)
+        .map(|a| a.patterns.len())
+        .sum();
+    if invalid.is_empty() && invalid_splits.is_empty() {
+        println!("{count} patterns, all valid");
+        return;
+    }
+    for pattern in &invalid {
+        println!("{pattern}");
+    }
+    for split in &invalid_splits {
+        println!("{split}");
+    }
+    if !invalid.is_empty() {
+        eprintln!("{} invalid patterns out of {count}", invalid.len());
+    }
+    if !invalid_splits.is_empty() {
+        eprintln!("{} invalid shares", invalid_splits.len());
+    }
+    std::process::exit(1);
+}
+
+/// Check the examples of the assignments, exiting with status 1 if one fails.
+fn check(spec_path: &str) {
+    let compiled = CompiledSpec::new(load_spec(spec_path)).unwrap_or_else(|e| {
+        eprintln!("Ventilation error: {e}");
+        std::process::exit(1);
+    });
+    let count: usize = compiled
+        .spec()
+        .assignments
+        .iter()
+        .chain(&compiled.spec().income)
+        .map(|a| a.examples.len() + a.counter_examples.len())
+        .sum();
+    let failures = check_examples(&compiled);
+    if failures.is_empty() {
+        println!("{count} examples, all passed");
+        return;
+    }
+    for failure in &failures {
+        print!("{failure}");
+    }
+    eprintln!("{} failed examples out of {count}", failures.len());
+    std::process::exit(1);
+}
+
 fn main() {
-    let releves_path = match std::env::args().nth(1) {
-        Some(p) => p,
-        None => {
-            eprintln!("Usage: my-bank-statements-ventilate <releves.yml> <ventilation_spec.yml> [output.yml]");
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(spec_path) = option(&mut args, "--validate", USAGE) {
+        validate(&spec_path);
+        return;
+    }
+    if let Some(spec_path) = option(&mut args, "--check", USAGE) {
+        check(&spec_path);
+        return;
+    }
+    let period: Option<Period> = option(&mut args, "--period", USAGE).map(|p| {
+        p.parse().unwrap_or_else(|e| {
+            eprintln!("{e}");
             std::process::exit(1);
-        }
-    };
+        })
+    });
+    let date_field: DateField = option(&mut args, "--date", USAGE)
+        .map(|d| {
+            d.parse().unwrap_or_else(|e| {
+                eprintln!("{e}");
+                std::process::exit(1);
+            })
+        })
+        .unwrap_or(DateField::Date);
 
-    let spec_path = match std::env::args().nth(2) {
-        Some(p) => p,
-        None => {
-            eprintln!("Usage: my-bank-statements-ventilate <releves.yml> <ventilation_spec.yml> [output.yml]");
+    // Levels of the hierarchical categories shown in the pie charts
+    let depth: Option<usize> = option(&mut args, "--depth", USAGE).map(|d| match d.parse() {
+        Ok(depth) if depth > 0 => depth,
+        _ => {
+            eprintln!("Bad depth {d}, expected a number of levels");
             std::process::exit(1);
         }
+    });
+
+    let overrides: Vec<Override> =
+        yaml_option(&mut args, "--overrides", "overrides", USAGE).unwrap_or_default();
+
+    let explain_query = option(&mut args, "--explain", USAGE);
+    // Fail on ambiguous matches whatever the resolution of the spec
+    let strict = flag(&mut args, "--strict");
+    let lint_mode = flag(&mut args, "--lint");
+
+    let (releves_path, spec_path) = match args.as_slice() {
+        [releves, spec, ..] => (releves.clone(), spec.clone()),
+        _ => usage(),
     };
 
-    let output_path = std::env::args()
-        .nth(3)
+    let output_path = args
+        .get(2)
+        .cloned()
         .unwrap_or_else(|| "ventilation.yml".to_string());
 
     // Read releves
@@ -36,60 +165,142 @@ fn main() {
     });
 
     // Read ventilation spec
-    let spec_content = fs::read_to_string(&spec_path).unwrap_or_else(|e| {
-        eprintln!("Failed to read ventilation spec {spec_path}: {e}");
-        std::process::exit(1);
-    });
+    let mut spec = load_spec(&spec_path);
 
-    let spec: VentilationSpec = serde_yaml::from_str(&spec_content).unwrap_or_else(|e| {
-        eprintln!("Failed to parse ventilation spec: {e}");
-        std::process::exit(1);
-    });
+    if strict {
+        spec.resolution = Resolution::Strict;
+    }
+    spec.overrides.extend(overrides);
+
+    if let Some(query) = explain_query {
+        let compiled = CompiledSpec::new(spec).unwrap_or_else(|e| {
+            eprintln!("Ventilation error: {e}");
+            std::process::exit(1);
+        });
+        explain_operations(&compiled, &releves, &query);
+        return;
+    }
+
+    if lint_mode {
+        let compiled = CompiledSpec::new(spec).unwrap_or_else(|e| {
+            eprintln!("Ventilation error: {e}");
+            std::process::exit(1);
+        });
+        print!("{}", lint_text(&lint(&compiled, &releves)));
+        return;
+    }
 
     // Run ventilation
     match ventilate(spec, &releves) {
         Ok(result) => {
+            let ambiguous: Vec<&AmbiguousMatch> = result
+                .debit
+                .ambiguous_matches
+                .iter()
+                .chain(&result.credit.ambiguous_matches)
+                .collect();
+            for warning in &ambiguous {
+                eprintln!(
+                    "Warning: {} {} matches {} assignments, assigned to {}",
+                    warning.date,
+                    warning.operation,
+                    warning.matches.len(),
+                    warning.chosen
+                );
+            }
+
             let yaml =
                 serde_yaml::to_string(&result).expect("Failed to serialize ventilation to YAML");
             fs::write(&output_path, &yaml).expect("Failed to write ventilation YAML");
             println!("Wrote ventilation to {output_path}");
 
             // Generate Mermaid pie chart markdown
-            let mut md = format!(
-                "# Ventilation: {}\n\n```mermaid\npie showData\n",
-                result.spec.name
-            );
-            // Build a set of ignored categories
-            let ignored: std::collections::HashSet<&str> = result
-                .spec
-                .assignments
-                .iter()
-                .filter(|a| a.ignore)
-                .map(|a| a.name.as_str())
-                .collect();
+            let mut md = format!("# Ventilation: {}\n\n", result.spec.name);
+            md.push_str("## Dépenses\n\n");
+            md.push_str(&pie_chart(&result.debit, &result.spec.assignments, depth));
+            if result.credit.not_assigned > 0 || !result.credit.ventilation.is_empty() {
+                md.push_str("\n## Recettes\n\n");
+                md.push_str(&pie_chart(&result.credit, &result.spec.income, depth));
+            }
 
-            // Collect and sort categories by amount (descending)
-            let mut entries: Vec<(&String, i64)> = result
-                .ventilation
-                .iter()
-                .filter(|(name, _)| !ignored.contains(name.as_str()))
-                .map(|(name, &amount)| (name, amount))
-                .collect();
+            // Money in vs money out
+            let total = |breakdown: &Breakdown| -> i64 {
+                breakdown.ventilation.values().sum::<i64>() + breakdown.not_assigned
+            };
+            let (recettes, depenses) = (total(&result.credit), total(&result.debit));
+            md.push_str("\n## Entrées / sorties\n\n| | Montant |\n|---|---:|\n");
+            md.push_str(&format!("| Recettes | {} |\n", euros(recettes)));
+            md.push_str(&format!("| Dépenses | {} |\n", euros(depenses)));
+            md.push_str(&format!("| Solde | {} |\n", euros(recettes - depenses)));
+
+            // Category × period tables
+            if let Some(period) = period {
+                let by_period = ventilate_by_period(&result, period, date_field);
+                let period_path = match output_path.strip_suffix(".yml") {
+                    Some(stem) => format!("{stem}-by-period.yml"),
+                    None => format!("{output_path}-by-period.yml"),
+                };
+                let yaml = serde_yaml::to_string(&by_period)
+                    .expect("Failed to serialize ventilation by period to YAML");
+                fs::write(&period_path, &yaml).expect("Failed to write ventilation by period YAML");
+                println!("Wrote ventilation by period to {period_path}");
 
-            // Add "Non assigné" if there are unassigned operations
-            let non_assigne = "Non assigné".to_string();
-            if result.not_assigned > 0 {
-                entries.push((&non_assigne, result.not_assigned));
+                md.push_str("\n## Dépenses par période\n\n");
+                md.push_str(&period_table(
+                    &by_period.debit,
+                    &by_period.periods,
+                    &result.spec.assignments,
+                ));
+                if !by_period.credit.totals.is_empty() {
+                    md.push_str("\n## Recettes par période\n\n");
+                    md.push_str(&period_table(
+                        &by_period.credit,
+                        &by_period.periods,
+                        &result.spec.income,
+                    ));
+                }
             }
 
-            // Sort by amount descending
-            entries.sort_by(|a, b| b.1.cmp(&a.1));
+            if !result.transfers.is_empty() {
+                md.push_str("\n## Virements internes\n\n");
+                md.push_str("| Date | Opération | Montant | De | Vers |\n");
+                md.push_str("|---|---|---:|---|---|\n");
+                for transfer in &result.transfers {
+                    let Some(leg) = transfer.outgoing.as_ref().or(transfer.incoming.as_ref())
+                    else {
+                        continue;
+                    };
+                    md.push_str(&format!(
+                        "| {} | {} | {} | {} | {} |\n",
+                        leg.operation.date,
+                        leg.operation.nature_des_operations,
+                        euros(transfer.montant),
+                        transfer.from().unwrap_or("?"),
+                        transfer.to().unwrap_or("?")
+                    ));
+                }
+            }
 
-            for (name, amount) in entries {
-                let euros = amount as f64 / 100.0;
-                md.push_str(&format!("    \"{name}\" : {euros:.2}\n"));
+            if !ambiguous.is_empty() {
+                md.push_str("\n## Correspondances multiples\n\n");
+                md.push_str("| Date | Opération | Montant | Assignations | Retenue |\n");
+                md.push_str("|---|---|---:|---|---|\n");
+                for warning in &ambiguous {
+                    let names: Vec<&str> = warning
+                        .matches
+                        .iter()
+                        .map(|m| m.assignment.as_str())
+                        .collect();
+                    md.push_str(&format!(
+                        "| {} | {} | {} | {} | {} |\n",
+                        warning.date,
+                        warning.operation,
+                        euros(warning.montant),
+                        names.join(", "),
+                        warning.chosen
+                    ));
+                }
             }
-            md.push_str("```\n");
 
             fs::write("ventilation.md", &md).expect("Failed to write ventilation.md");
             println!("Wrote ventilation.md");
@@ -100,3 +311,187 @@ fn main() {
         }
     }
 }
+
+/// The names of the ignored assignments, left out of the charts and tables
+fn ignored(assignments: &[Assignment]) -> std::collections::HashSet<&str> {
+    assignments
+        .iter()
+        .filter(|a| a.ignore)
+        .map(|a| a.name.as_str())
+        .collect()
+}
+
+/// A Mermaid pie chart of a breakdown, without the ignored assignments.
+/// With a `depth`, the categories are rolled up to their first `depth` levels.
+fn pie_chart(breakdown: &Breakdown, assignments: &[Assignment], depth: Option<usize>) -> String {
+    let mut md = "```mermaid\npie showData\n".to_string();
+    let ignored = ignored(assignments);
+
+    // Sum the categories shown
+    let mut amounts: IndexMap<String, i64> = IndexMap::new();
+    for (name, &amount) in &breakdown.ventilation {
+        if ignored.contains(name.as_str()) {
+            continue;
+        }
+        let shown = match depth {
+            Some(depth) => truncate_category(name, depth),
+            None => name.clone(),
+        };
+        *amounts.entry(shown).or_insert(0) += amount;
+    }
+    let mut entries: Vec<(&String, i64)> = amounts
+        .iter()
+        .map(|(name, &amount)| (name, amount))
+        .collect();
+
+    // Add "Non assigné" if there are unassigned operations
+    let non_assigne = "Non assigné".to_string();
+    if breakdown.not_assigned > 0 {
+        entries.push((&non_assigne, breakdown.not_assigned));
+    }
+
+    // Sort by amount descending, ties in the order of the ventilation
+    entries.sort_by_key(|e| std::cmp::Reverse(e.1));
+
+    for (name, amount) in entries {
+        md.push_str(&format!("    \"{name}\" : {}\n", euros(amount)));
+    }
+    md.push_str("```\n");
+    md
+}
+
+/// A Markdown table with one row per category and one column per period, with
+/// totals, without the ignored assignments.
+fn period_table(matrix: &PeriodMatrix, periods: &[String], assignments: &[Assignment]) -> String {
+    let ignored = ignored(assignments);
+    let row = |name: &str, amounts: &std::collections::BTreeMap<String, i64>| -> String {
+        let mut line = format!("| {name} |");
+        for period in periods {
+            let amount = amounts.get(period).copied().unwrap_or(0);
+            line.push_str(&format!(" {} |", euros(amount)));
+        }
+        line.push_str(&format!(" {} |\n", euros(amounts.values().sum())));
+        line
+    };
+
+    let mut md = "| Catégorie |".to_string();
+    for period in periods {
+        md.push_str(&format!(" {period} |"));
+    }
+    md.push_str(" Total |\n|---|");
+    md.push_str(&"---:|".repeat(periods.len() + 1));
+    md.push('\n');
+    let mut totals = matrix.totals.clone();
+    for (name, amounts) in &matrix.categories {
+        if ignored.contains(name.as_str()) {
+            for (period, amount) in amounts {
+                *totals.entry(period.clone()).or_insert(0) -= amount;
+            }
+            continue;
+        }
+        md.push_str(&row(name, amounts));
+    }
+    if !matrix.not_assigned.is_empty() {
+        md.push_str(&row("Non assigné", &matrix.not_assigned));
+    }
+    md.push_str(&row("**Total**", &totals));
+    md
+}
+
+/// Explain the operations found by `query`: a `2024-01-05:4500` date and
+/// amount in centimes, negative for a debit only, an operation id, or else a
+/// part of the description.
+fn explain_operations(compiled: &CompiledSpec, releves: &[Releve], query: &str) {
+    let date_amount = query.split_once(':').and_then(|(date, montant)| {
+        Some(OperationQuery::DateAmount {
+            date: date.parse().ok()?,
+            montant: montant.parse().ok()?,
+        })
+    });
+    let query = date_amount.unwrap_or_else(|| {
+        let id = OperationQuery::Id(query.to_string());
+        if find_operations(releves, &id).is_empty() {
+            OperationQuery::Description(query.to_string())
+        } else {
+            id
+        }
+    });
+
+    let operations = find_operations(releves, &query);
+    if operations.is_empty() {
+        eprintln!("No operation found for {query:?}");
+        std::process::exit(1);
+    }
+    let transfers = find_transfers(releves, &compiled.spec().transfers);
+    for (index, found) in operations.into_iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        let transfer = transfer_at(&transfers, found.position);
+        print!(
+            "{}",
+            explain(compiled, found.operation, found.account, transfer)
+        );
+    }
+}
+
+/// The lint report as text: the findings, then the coverage of each side.
+fn lint_text(report: &LintReport) -> String {
+    let mut text = String::new();
+    text.push_str(&format!(
+        "Dead patterns ({}):\n",
+        report.dead_patterns.len()
+    ));
+    for pattern in &report.dead_patterns {
+        text.push_str(&format!("  {pattern}\n"));
+    }
+    text.push_str(&format!(
+        "Subsumed patterns ({}):\n",
+        report.subsumed_patterns.len()
+    ));
+    for subsumed in &report.subsumed_patterns {
+        text.push_str(&format!(
+            "  {}\n    matches only operations of {} ({} operations)\n",
+            subsumed.pattern, subsumed.by, subsumed.operations
+        ));
+    }
+    text.push_str(&format!(
+        "Overlapping patterns ({}):\n",
+        report.overlaps.len()
+    ));
+    for overlap in &report.overlaps {
+        text.push_str(&format!(
+            "  {}\n    and {}: {} operations, {}\n",
+            overlap.first,
+            overlap.second,
+            overlap.operations,
+            euros(overlap.montant)
+        ));
+    }
+
+    let side = |title: &str, coverage: &Coverage| -> String {
+        let mut text = format!("{title}:\n");
+        for assignment in &coverage.assignments {
+            text.push_str(&format!(
+                "  {}: {} operations, {}\n",
+                assignment.name,
+                assignment.operations,
+                euros(assignment.montant)
+            ));
+        }
+        text.push_str(&format!(
+            "  Categorised: {} of {} operations, {} of {} ({:.1}%)\n",
+            coverage.categorised_operations,
+            coverage.operations,
+            euros(coverage.categorised_montant),
+            euros(coverage.montant),
+            coverage.percent()
+        ));
+        text
+    };
+    text.push_str(&side("Debits", &report.debit));
+    if report.credit.operations > 0 {
+        text.push_str(&side("Credits", &report.credit));
+    }
+    text
+}
@@ -0,0 +1,150 @@
+use chrono::NaiveDate;
+use std::fmt::Write;
+
+use crate::parser::{Operation, OperationKind, Releve, Solde, SoldeType};
+
+/// Statements are all in euros
+const CURRENCY: &str = "EUR";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn amount(centimes: i64) -> String {
+    format!("{}.{:02}", centimes / 100, centimes % 100)
+}
+
+fn indicator(solde_type: &SoldeType) -> &'static str {
+    match solde_type {
+        SoldeType::Credit => "CRDT",
+        SoldeType::Debit => "DBIT",
+    }
+}
+
+fn write_balance(xml: &mut String, code: &str, solde: &Solde, date: NaiveDate) {
+    let _ = write!(
+        xml,
+        r#"      <Bal>
+        <Tp><CdOrPrtry><Cd>{code}</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="{CURRENCY}">{}</Amt>
+        <CdtDbtInd>{}</CdtDbtInd>
+        <Dt><Dt>{date}</Dt></Dt>
+      </Bal>
+"#,
+        amount(solde.montant),
+        indicator(&solde.solde_type),
+    );
+}
+
+fn write_entry(xml: &mut String, operation: &Operation) {
+    let nature = escape(&operation.nature_des_operations);
+    let kind = operation
+        .details
+        .as_ref()
+        .map_or(OperationKind::Autre, |d| d.kind.clone());
+    let kind = format!("{kind:?}");
+    let _ = write!(
+        xml,
+        r#"      <Ntry>
+        <Amt Ccy="{CURRENCY}">{}</Amt>
+        <CdtDbtInd>{}</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><Dt>{}</Dt></BookgDt>
+        <ValDt><Dt>{}</Dt></ValDt>
+        <BkTxCd><Prtry><Cd>{kind}</Cd></Prtry></BkTxCd>
+        <AddtlNtryInf>{nature}</AddtlNtryInf>
+      </Ntry>
+"#,
+        amount(operation.montant),
+        indicator(&operation.montant_type),
+        operation.date,
+        operation.valeur,
+    );
+}
+
+fn write_summary(xml: &mut String, releve: &Releve) {
+    let count = |montant_type: fn(&SoldeType) -> bool| {
+        releve
+            .operations
+            .iter()
+            .filter(|op| montant_type(&op.montant_type))
+            .count()
+    };
+    let _ = write!(
+        xml,
+        r#"      <TxsSummry>
+        <TtlCdtNtries><NbOfNtries>{}</NbOfNtries><Sum>{}</Sum></TtlCdtNtries>
+        <TtlDbtNtries><NbOfNtries>{}</NbOfNtries><Sum>{}</Sum></TtlDbtNtries>
+      </TxsSummry>
+"#,
+        count(|t| matches!(t, SoldeType::Credit)),
+        amount(releve.total_des_operations_credit),
+        count(|t| matches!(t, SoldeType::Debit)),
+        amount(releve.total_des_operations_debit),
+    );
+}
+
+/// Write releves as an ISO 20022 camt.053.001.02 document, one `Stmt` per releve.
+///
+/// The opening balance is dated the day before the first operation (or the
+/// date du releve when there is none). The creation time is the date of the
+/// last releve, so that the same releves always give the same document.
+pub fn to_camt053(releves: &[Releve], iban: &str) -> String {
+    let created = releves
+        .iter()
+        .map(|r| r.date_du_releve)
+        .max()
+        .unwrap_or_default();
+    let iban = escape(iban);
+
+    let mut xml = String::new();
+    let _ = write!(
+        xml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <GrpHdr>
+      <MsgId>{iban}-{created}</MsgId>
+      <CreDtTm>{created}T00:00:00</CreDtTm>
+    </GrpHdr>
+"#
+    );
+
+    for releve in releves {
+        let opening_date = releve
+            .operations
+            .iter()
+            .map(|op| op.date)
+            .min()
+            .and_then(|d| d.pred_opt())
+            .unwrap_or(releve.date_du_releve);
+        let _ = write!(
+            xml,
+            r#"    <Stmt>
+      <Id>{iban}-{}</Id>
+      <CreDtTm>{}T00:00:00</CreDtTm>
+      <Acct><Id><IBAN>{iban}</IBAN></Id><Ccy>{CURRENCY}</Ccy></Acct>
+"#,
+            releve.date_du_releve, releve.date_du_releve,
+        );
+        write_balance(&mut xml, "OPBD", &releve.solde_ouverture, opening_date);
+        write_balance(
+            &mut xml,
+            "CLBD",
+            &releve.solde_cloture,
+            releve.date_du_releve,
+        );
+        write_summary(&mut xml, releve);
+        for operation in &releve.operations {
+            write_entry(&mut xml, operation);
+        }
+        xml.push_str("    </Stmt>\n");
+    }
+
+    xml.push_str("  </BkToCstmrStmt>\n</Document>\n");
+    xml
+}
@@ -0,0 +1,403 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::parser::continuity::signed_solde;
+use crate::parser::{Operation, Releve, SoldeType};
+use crate::ventilation::compiled::CompiledSpec;
+use crate::ventilation::model::{VentilationSpec, category_levels};
+use crate::ventilation::transfers::find_transfers;
+use crate::ventilation::ventilate::VentilateError;
+
+/// Plain-text accounting journal syntax.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JournalFormat {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+impl std::str::FromStr for JournalFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ledger" => Ok(JournalFormat::Ledger),
+            "hledger" => Ok(JournalFormat::Hledger),
+            "beancount" => Ok(JournalFormat::Beancount),
+            _ => Err(format!(
+                "unknown journal format '{s}' (ledger, hledger or beancount)"
+            )),
+        }
+    }
+}
+
+fn default_bank_account() -> String {
+    "Assets:Bank".to_string()
+}
+
+fn default_expense_prefix() -> String {
+    "Expenses".to_string()
+}
+
+fn default_income_prefix() -> String {
+    "Income".to_string()
+}
+
+fn default_suspense_account() -> String {
+    "Expenses:Unassigned".to_string()
+}
+
+fn default_transfer_prefix() -> String {
+    "Assets".to_string()
+}
+
+fn default_opening_account() -> String {
+    "Equity:Opening-Balances".to_string()
+}
+
+fn default_currency() -> String {
+    "EUR".to_string()
+}
+
+/// Accounts used in the journal.
+///
+/// A debit matched by an assignment goes to `<expense_prefix>:<assignment name>`,
+/// a credit matched by an income assignment to `<income_prefix>:<assignment name>`,
+/// unless the name is listed in `accounts`. Operations matched by no assignment go to `suspense_account`.
+/// An internal transfer goes to `<transfer_prefix>:<own account name>`, the
+/// other own account of the `transfers` of the spec, unless its name is listed
+/// in `accounts`.
+/// The operations of a releve are booked on `bank_account`, or on
+/// `<bank_account>:<account>` when the releves belong to several accounts,
+/// unless their `account` is listed in `bank_accounts`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JournalOptions {
+    #[serde(default = "default_bank_account")]
+    pub bank_account: String,
+    #[serde(default = "default_expense_prefix")]
+    pub expense_prefix: String,
+    #[serde(default = "default_income_prefix")]
+    pub income_prefix: String,
+    #[serde(default = "default_suspense_account")]
+    pub suspense_account: String,
+    #[serde(default = "default_transfer_prefix")]
+    pub transfer_prefix: String,
+    /// Counterpart of the opening balance of the first releve of each account
+    #[serde(default = "default_opening_account")]
+    pub opening_account: String,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    /// Assignment name -> full account name
+    #[serde(default)]
+    pub accounts: HashMap<String, String>,
+    /// `account` of the releves -> full bank account name
+    #[serde(default)]
+    pub bank_accounts: HashMap<String, String>,
+}
+
+impl Default for JournalOptions {
+    fn default() -> Self {
+        JournalOptions {
+            bank_account: default_bank_account(),
+            expense_prefix: default_expense_prefix(),
+            income_prefix: default_income_prefix(),
+            suspense_account: default_suspense_account(),
+            transfer_prefix: default_transfer_prefix(),
+            opening_account: default_opening_account(),
+            currency: default_currency(),
+            accounts: HashMap::new(),
+            bank_accounts: HashMap::new(),
+        }
+    }
+}
+
+/// Beancount account components start with a capital letter and
+/// only contain letters, digits and dashes: "Courses alimentaires" -> "Courses-alimentaires".
+fn beancount_component(name: &str) -> String {
+    let mut component = String::new();
+    for c in name.chars() {
+        let c = match c {
+            'à' | 'â' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' => 'i',
+            'ô' | 'ö' => 'o',
+            'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            'À' | 'Â' => 'A',
+            'É' | 'È' | 'Ê' => 'E',
+            'Ç' => 'C',
+            c if c.is_ascii_alphanumeric() => c,
+            _ => '-',
+        };
+        if c != '-' || !component.is_empty() && !component.ends_with('-') {
+            component.push(c);
+        }
+    }
+    let component = component.trim_end_matches('-');
+    let mut chars = component.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() => {
+            format!("{}{}", first.to_ascii_uppercase(), chars.as_str())
+        }
+        Some(_) => format!("X{component}"),
+        None => "Unnamed".to_string(),
+    }
+}
+
+struct Journal<'a> {
+    format: JournalFormat,
+    options: &'a JournalOptions,
+    text: String,
+    accounts: BTreeSet<String>,
+}
+
+impl Journal<'_> {
+    fn account(&mut self, prefix: &str, assignment: &str) -> String {
+        let account = match self.options.accounts.get(assignment) {
+            Some(account) => account.clone(),
+            None => self.sub_account(prefix, assignment),
+        };
+        self.accounts.insert(account.clone());
+        account
+    }
+
+    /// `<prefix>:<name>`, each level of a hierarchical name being a sub-account
+    fn sub_account(&self, prefix: &str, name: &str) -> String {
+        match self.format {
+            JournalFormat::Beancount => category_levels(name)
+                .map(beancount_component)
+                .fold(prefix.to_string(), |account, c| format!("{account}:{c}")),
+            // Two spaces end the account name in ledger
+            _ => category_levels(name)
+                .map(|level| level.split_whitespace().collect::<Vec<_>>().join(" "))
+                .fold(prefix.to_string(), |account, c| format!("{account}:{c}")),
+        }
+    }
+
+    /// The bank account the operations of a releve of `account` are booked on
+    fn bank_account(&mut self, account: Option<&str>, several_accounts: bool) -> String {
+        let bank_account = match account {
+            Some(account) => match self.options.bank_accounts.get(account) {
+                Some(bank_account) => bank_account.clone(),
+                None if several_accounts => self.sub_account(&self.options.bank_account, account),
+                None => self.options.bank_account.clone(),
+            },
+            None => self.options.bank_account.clone(),
+        };
+        self.accounts.insert(bank_account.clone());
+        bank_account
+    }
+
+    fn amount(&self, centimes: i64) -> String {
+        let sign = if centimes < 0 { "-" } else { "" };
+        let centimes = centimes.abs();
+        format!(
+            "{sign}{}.{:02} {}",
+            centimes / 100,
+            centimes % 100,
+            self.options.currency
+        )
+    }
+
+    fn header(&mut self, date: NaiveDate, valeur: Option<NaiveDate>, description: &str) {
+        let _ = match self.format {
+            JournalFormat::Beancount => writeln!(
+                self.text,
+                "{date} * \"{}\"",
+                description.replace('\\', "\\\\").replace('"', "\\\"")
+            ),
+            // The valeur date is the secondary (effective) date
+            _ => match valeur.filter(|v| *v != date) {
+                Some(valeur) => writeln!(self.text, "{date}={valeur} * {description}"),
+                None => writeln!(self.text, "{date} * {description}"),
+            },
+        };
+    }
+
+    fn posting(&mut self, account: &str, centimes: i64) {
+        let indent = if self.format == JournalFormat::Beancount {
+            "  "
+        } else {
+            "    "
+        };
+        let _ = writeln!(self.text, "{indent}{account}  {}", self.amount(centimes));
+    }
+
+    /// An operation of `bank_account`, its amount going to `counter_accounts`
+    /// by parts.
+    fn transaction(
+        &mut self,
+        bank_account: &str,
+        operation: &Operation,
+        counter_accounts: &[(String, i64)],
+    ) {
+        let signed = match operation.montant_type {
+            SoldeType::Debit => -operation.montant,
+            SoldeType::Credit => operation.montant,
+        };
+        self.header(
+            operation.date,
+            Some(operation.valeur),
+            &operation.nature_des_operations,
+        );
+        for (account, part) in counter_accounts {
+            let part = match operation.montant_type {
+                SoldeType::Debit => -part,
+                SoldeType::Credit => *part,
+            };
+            self.posting(account, -part);
+        }
+        self.posting(bank_account, signed);
+        self.text.push('\n');
+    }
+
+    /// Assert the balance of `bank_account` before the operations of `date`
+    /// (`before` is true) or after them.
+    fn balance_assertion(
+        &mut self,
+        bank_account: &str,
+        date: NaiveDate,
+        centimes: i64,
+        before: bool,
+        label: &str,
+    ) {
+        let amount = self.amount(centimes);
+        let _ = match self.format {
+            // Beancount checks the balance at the beginning of the day
+            JournalFormat::Beancount => {
+                let date = if before {
+                    date
+                } else {
+                    date.succ_opt().unwrap_or(date)
+                };
+                writeln!(self.text, "{date} balance {bank_account}  {amount}\n")
+            }
+            _ => writeln!(
+                self.text,
+                "{date} * {label}\n    {bank_account}  {} = {amount}\n",
+                self.amount(0)
+            ),
+        };
+    }
+}
+
+/// Write releves as a ledger, hledger or beancount journal, the counterpart
+/// of each operation being given by the assignments of `spec`.
+///
+/// The opening balance of the first releve of each account is booked against
+/// `opening_account`, the soldes of every releve are written as balance
+/// assertions around its operations. Internal transfers are booked against
+/// the other own account rather than a category.
+pub fn to_journal(
+    releves: &[Releve],
+    spec: &VentilationSpec,
+    format: JournalFormat,
+    options: &JournalOptions,
+) -> Result<String, VentilateError> {
+    let mut journal = Journal {
+        format,
+        options,
+        text: String::new(),
+        accounts: BTreeSet::new(),
+    };
+
+    let compiled = CompiledSpec::new(spec.clone())?;
+    let transfers = find_transfers(releves, &spec.transfers);
+    // Other own account by position of the operation in the releves
+    let transfer_accounts: HashMap<(usize, usize), &str> = transfers
+        .iter()
+        .flat_map(|t| {
+            t.positions()
+                .filter_map(move |p| Some((p, t.other_account(p)?)))
+        })
+        .collect();
+    let several_accounts = releves
+        .iter()
+        .filter_map(|r| r.account.as_deref())
+        .collect::<HashSet<_>>()
+        .len()
+        > 1;
+    let mut releves: Vec<(usize, &Releve)> = releves.iter().enumerate().collect();
+    releves.sort_by_key(|(_, r)| r.date_du_releve);
+
+    let mut opened: HashSet<String> = HashSet::new();
+    for &(r, releve) in &releves {
+        let bank_account = journal.bank_account(releve.account.as_deref(), several_accounts);
+        let mut operations: Vec<(usize, &Operation)> =
+            releve.operations.iter().enumerate().collect();
+        operations.sort_by_key(|(_, op)| op.date);
+        let start = operations
+            .first()
+            .map_or(releve.date_du_releve, |(_, op)| op.date);
+        let ouverture = signed_solde(&releve.solde_ouverture);
+
+        if opened.insert(bank_account.clone()) {
+            journal.header(start, None, "Solde ouverture");
+            let opening_account = options.opening_account.clone();
+            journal.accounts.insert(opening_account.clone());
+            journal.posting(&bank_account, ouverture);
+            journal.posting(&opening_account, -ouverture);
+            journal.text.push('\n');
+        } else {
+            journal.balance_assertion(&bank_account, start, ouverture, true, "Solde ouverture");
+        }
+
+        for (o, operation) in operations {
+            if let Some(other) = transfer_accounts.get(&(r, o)) {
+                let account = journal.account(&options.transfer_prefix, other);
+                journal.transaction(&bank_account, operation, &[(account, operation.montant)]);
+                continue;
+            }
+            let (assigned, _) = compiled.assign(operation, releve.account.as_deref())?;
+            let prefix = match operation.montant_type {
+                SoldeType::Debit => &options.expense_prefix,
+                SoldeType::Credit => &options.income_prefix,
+            };
+            let counter_accounts: Vec<(String, i64)> = match &assigned {
+                Some(m) => compiled
+                    .allocation(m, operation)
+                    .into_iter()
+                    .map(|(name, part)| (journal.account(prefix, &name), part))
+                    .collect(),
+                None => {
+                    journal.accounts.insert(options.suspense_account.clone());
+                    vec![(options.suspense_account.clone(), operation.montant)]
+                }
+            };
+            journal.transaction(&bank_account, operation, &counter_accounts);
+        }
+
+        journal.balance_assertion(
+            &bank_account,
+            releve.date_du_releve,
+            signed_solde(&releve.solde_cloture),
+            false,
+            "Solde cloture",
+        );
+    }
+
+    if format != JournalFormat::Beancount {
+        return Ok(journal.text);
+    }
+
+    // Beancount accounts must be opened before use
+    let first = releves
+        .iter()
+        .flat_map(|(_, r)| {
+            r.operations
+                .iter()
+                .map(|op| op.date)
+                .chain([r.date_du_releve])
+        })
+        .min()
+        .unwrap_or_default();
+    let mut text = String::new();
+    for account in &journal.accounts {
+        let _ = writeln!(text, "{first} open {account}");
+    }
+    text.push('\n');
+    text.push_str(&journal.text);
+    Ok(text)
+}
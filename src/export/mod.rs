@@ -0,0 +1,5 @@
+pub mod camt053;
+pub mod journal;
+
+pub use camt053::to_camt053;
+pub use journal::{JournalFormat, JournalOptions, to_journal};
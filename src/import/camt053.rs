@@ -0,0 +1,162 @@
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+
+use super::common::{ImportError, operation, parse_decimal, read_file};
+use crate::parser::{Releve, Solde, SoldeType};
+
+/// The descendant of `node` following `path`, by local name (namespaces ignored).
+fn find<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| {
+        node.children()
+            .find(|c| c.is_element() && c.tag_name().name() == *name)
+    })
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |c| c.is_element() && c.tag_name().name() == name)
+}
+
+fn text<'a>(node: Node<'a, '_>, path: &[&str]) -> Option<&'a str> {
+    find(node, path).and_then(|n| n.text()).map(str::trim)
+}
+
+fn line(node: Node) -> usize {
+    node.document().text_pos_at(node.range().start).row as usize
+}
+
+/// `<Dt>2024-01-31</Dt>` or `<DtTm>2024-01-31T18:00:00</DtTm>`
+fn date(node: Node) -> Option<NaiveDate> {
+    let value = text(node, &["Dt"]).or_else(|| text(node, &["DtTm"]))?;
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn solde_type(node: Node) -> SoldeType {
+    match text(node, &["CdtDbtInd"]) {
+        Some("DBIT") => SoldeType::Debit,
+        _ => SoldeType::Credit,
+    }
+}
+
+fn amount(node: Node) -> Result<i64, ImportError> {
+    let value =
+        text(node, &["Amt"]).ok_or_else(|| ImportError::format(line(node), "missing Amt"))?;
+    parse_decimal(value, false)
+        .ok_or_else(|| ImportError::format(line(node), format!("bad amount '{value}'")))
+}
+
+/// A balance of the statement, by its code: OPBD (opening booked), PRCD
+/// (previously closed booked), CLBD (closing booked)...
+fn balance(stmt: Node, codes: &[&str]) -> Result<Option<(NaiveDate, Solde)>, ImportError> {
+    let Some(bal) = children(stmt, "Bal").find(|bal| {
+        text(*bal, &["Tp", "CdOrPrtry", "Cd"]).is_some_and(|code| codes.contains(&code))
+    }) else {
+        return Ok(None);
+    };
+    let dt = find(bal, &["Dt"])
+        .and_then(date)
+        .ok_or_else(|| ImportError::format(line(bal), "balance without date"))?;
+    let solde = Solde {
+        solde_type: solde_type(bal),
+        montant: amount(bal)?,
+    };
+    Ok(Some((dt, solde)))
+}
+
+/// The sum of the credit or debit entries announced in the transactions summary.
+fn summary_total(stmt: Node, name: &str) -> Option<i64> {
+    parse_decimal(text(stmt, &["TxsSummry", name, "Sum"])?, false)
+}
+
+fn parse_stmt(stmt: Node) -> Result<Releve, ImportError> {
+    let mut operations = Vec::new();
+    for ntry in children(stmt, "Ntry") {
+        let booking_date = find(ntry, &["BookgDt"])
+            .and_then(date)
+            .ok_or_else(|| ImportError::format(line(ntry), "entry without BookgDt"))?;
+        let valeur = find(ntry, &["ValDt"]).and_then(date);
+        let montant = amount(ntry)?;
+        let signed = match solde_type(ntry) {
+            SoldeType::Debit => -montant,
+            SoldeType::Credit => montant,
+        };
+
+        // AddtlNtryInf is the label printed on statements, the remittance
+        // information is the fallback
+        let remittance: Vec<&str> = find(ntry, &["NtryDtls"])
+            .into_iter()
+            .flat_map(|dtls| children(dtls, "TxDtls"))
+            .flat_map(|tx| find(tx, &["RmtInf"]))
+            .flat_map(|rmt| children(rmt, "Ustrd"))
+            .filter_map(|ustrd| ustrd.text().map(str::trim))
+            .collect();
+        let nature = match text(ntry, &["AddtlNtryInf"]) {
+            Some(info) => info.to_string(),
+            None => remittance.join(" "),
+        };
+
+        operations.push(operation(booking_date, valeur, nature, signed));
+    }
+
+    let ouverture = balance(stmt, &["OPBD", "PRCD"])?
+        .ok_or_else(|| ImportError::format(line(stmt), "no OPBD balance"))?;
+    let (date_du_releve, solde_cloture) = balance(stmt, &["CLBD"])?
+        .ok_or_else(|| ImportError::format(line(stmt), "no CLBD balance"))?;
+
+    let check_debit: i64 = operations
+        .iter()
+        .filter(|op| matches!(op.montant_type, SoldeType::Debit))
+        .map(|op| op.montant)
+        .sum();
+    let check_credit: i64 = operations
+        .iter()
+        .filter(|op| matches!(op.montant_type, SoldeType::Credit))
+        .map(|op| op.montant)
+        .sum();
+
+    let mut releve = Releve {
+        date_du_releve,
+        solde_ouverture: ouverture.1,
+        solde_cloture,
+        total_des_operations_debit: summary_total(stmt, "TtlDbtNtries").unwrap_or(check_debit),
+        total_des_operations_credit: summary_total(stmt, "TtlCdtNtries").unwrap_or(check_credit),
+        check_debit,
+        check_credit,
+        operations,
+        account: text(stmt, &["Acct", "Id", "IBAN"]).map(str::to_string),
+    };
+    releve.assign_operation_ids();
+    Ok(releve)
+}
+
+/// Parse an ISO 20022 camt.053 (bank to customer statement) document, one
+/// releve per `Stmt`.
+///
+/// The OPBD (or PRCD) and CLBD balances become the soldes, the CLBD date the
+/// date du releve. The totals come from `TxsSummry` when present, so that the
+/// checks computed from the entries can be compared with them.
+pub fn parse_camt053(text: &str) -> Result<Vec<Releve>, ImportError> {
+    let document = Document::parse(text).map_err(|e| ImportError::Format {
+        line: e.pos().row as usize,
+        message: e.to_string(),
+    })?;
+    let root = document.root_element();
+    let statements = find(root, &["BkToCstmrStmt"])
+        .ok_or_else(|| ImportError::format(line(root), "not a camt.053 document"))?;
+
+    let releves = children(statements, "Stmt")
+        .map(parse_stmt)
+        .collect::<Result<Vec<_>, _>>()?;
+    if releves.is_empty() {
+        return Err(ImportError::NoOperations);
+    }
+    Ok(releves)
+}
+
+/// Import a camt.053 file.
+pub fn import_camt053(path: &str) -> Result<Vec<Releve>, ImportError> {
+    parse_camt053(&read_file(path)?)
+}
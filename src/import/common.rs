@@ -0,0 +1,157 @@
+use chrono::NaiveDate;
+use std::fmt;
+
+use crate::parser::{Operation, Releve, Solde, SoldeType, decompose};
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io {
+        path: String,
+        message: String,
+    },
+    /// A record that cannot be read, `line` starting at 1
+    Format {
+        line: usize,
+        message: String,
+    },
+    NoOperations,
+    /// The CSV delimiter must be a single byte
+    Delimiter(char),
+}
+
+impl ImportError {
+    pub(crate) fn format(line: usize, message: impl Into<String>) -> Self {
+        ImportError::Format {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io { path, message } => write!(f, "Cannot read {path}: {message}"),
+            ImportError::Format { line, message } => write!(f, "Line {line}: {message}"),
+            ImportError::NoOperations => write!(f, "No operations found"),
+            ImportError::Delimiter(c) => write!(f, "Delimiter '{c}' is not an ASCII character"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+pub(crate) fn read_file(path: &str) -> Result<String, ImportError> {
+    std::fs::read(path)
+        .map(|bytes| {
+            // Bank exports are often latin-1 rather than UTF-8
+            String::from_utf8(bytes)
+                .unwrap_or_else(|e| e.into_bytes().iter().map(|b| *b as char).collect())
+        })
+        .map_err(|e| ImportError::Io {
+            path: path.to_string(),
+            message: e.to_string(),
+        })
+}
+
+/// Parse a signed decimal amount to centimes: "-1 234,56" or "-1,234.56".
+pub(crate) fn parse_decimal(s: &str, decimal_comma: bool) -> Option<i64> {
+    let (thousands, decimal) = if decimal_comma {
+        ('.', ',')
+    } else {
+        (',', '.')
+    };
+    let cleaned: String = s
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != thousands && *c != '\'' && *c != '€')
+        .map(|c| if c == decimal { '.' } else { c })
+        .collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+    cleaned
+        .parse::<f64>()
+        .ok()
+        .map(|v| (v * 100.0).round() as i64)
+}
+
+/// An operation from a signed amount, negative for a debit.
+pub(crate) fn operation(
+    date: NaiveDate,
+    valeur: Option<NaiveDate>,
+    nature_des_operations: String,
+    signed_montant: i64,
+) -> Operation {
+    let montant_type = if signed_montant < 0 {
+        SoldeType::Debit
+    } else {
+        SoldeType::Credit
+    };
+    Operation {
+        id: None,
+        date,
+        details: Some(decompose(&nature_des_operations)),
+        nature_des_operations,
+        valeur: valeur.unwrap_or(date),
+        montant: signed_montant.abs(),
+        montant_type,
+    }
+}
+
+fn solde(signed: i64) -> Solde {
+    Solde {
+        solde_type: if signed < 0 {
+            SoldeType::Debit
+        } else {
+            SoldeType::Credit
+        },
+        montant: signed.abs(),
+    }
+}
+
+/// Build a releve from imported operations.
+///
+/// Exports have no totals, so they are computed from the operations. When the
+/// closing balance is known the opening balance is derived from it, otherwise
+/// both soldes are relative to an opening balance of 0.
+pub(crate) fn build_releve(
+    mut operations: Vec<Operation>,
+    solde_cloture: Option<(NaiveDate, i64)>,
+) -> Result<Releve, ImportError> {
+    operations.sort_by_key(|op| op.date);
+    let last_date = operations.last().map(|op| op.date);
+    let date_du_releve = solde_cloture
+        .map(|(date, _)| date)
+        .or(last_date)
+        .ok_or(ImportError::NoOperations)?;
+
+    let debit: i64 = operations
+        .iter()
+        .filter(|op| matches!(op.montant_type, SoldeType::Debit))
+        .map(|op| op.montant)
+        .sum();
+    let credit: i64 = operations
+        .iter()
+        .filter(|op| matches!(op.montant_type, SoldeType::Credit))
+        .map(|op| op.montant)
+        .sum();
+
+    let (ouverture, cloture) = match solde_cloture {
+        Some((_, cloture)) => (cloture - credit + debit, cloture),
+        None => (0, credit - debit),
+    };
+
+    let mut releve = Releve {
+        date_du_releve,
+        solde_ouverture: solde(ouverture),
+        solde_cloture: solde(cloture),
+        total_des_operations_debit: debit,
+        total_des_operations_credit: credit,
+        check_debit: debit,
+        check_credit: credit,
+        operations,
+        account: None,
+    };
+    releve.assign_operation_ids();
+    Ok(releve)
+}
@@ -0,0 +1,129 @@
+use chrono::NaiveDate;
+
+use super::common::{ImportError, build_releve, operation, parse_decimal, read_file};
+use super::model::{CsvColumn, CsvFormat};
+use crate::parser::Releve;
+
+fn column_index(
+    column: &CsvColumn,
+    headers: Option<&csv::StringRecord>,
+) -> Result<usize, ImportError> {
+    match column {
+        CsvColumn::Index(index) => Ok(*index),
+        CsvColumn::Name(name) => headers
+            .and_then(|h| h.iter().position(|field| field.trim() == name))
+            .ok_or_else(|| ImportError::format(1, format!("no column named '{name}'"))),
+    }
+}
+
+/// Parse the text of a CSV export.
+pub fn parse_csv(text: &str, format: &CsvFormat) -> Result<Releve, ImportError> {
+    if !format.delimiter.is_ascii() {
+        return Err(ImportError::Delimiter(format.delimiter));
+    }
+    let body: String = text
+        .lines()
+        .skip(format.skip_lines)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(format.delimiter as u8)
+        .has_headers(format.has_header)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+
+    let headers = if format.has_header {
+        Some(
+            reader
+                .headers()
+                .map_err(|e| ImportError::format(format.skip_lines + 1, e.to_string()))?
+                .clone(),
+        )
+    } else {
+        None
+    };
+    let index = |column: &CsvColumn| column_index(column, headers.as_ref());
+    let optional_index = |column: &Option<CsvColumn>| column.as_ref().map(index).transpose();
+
+    let date = index(&format.date)?;
+    let valeur = optional_index(&format.valeur)?;
+    let description = format
+        .description
+        .iter()
+        .map(index)
+        .collect::<Result<Vec<_>, _>>()?;
+    let amount = optional_index(&format.amount)?;
+    let debit = optional_index(&format.debit)?;
+    let credit = optional_index(&format.credit)?;
+    let balance = optional_index(&format.balance)?;
+    if amount.is_none() && debit.is_none() && credit.is_none() {
+        return Err(ImportError::format(
+            1,
+            "the format has neither an amount nor a debit/credit column",
+        ));
+    }
+
+    let mut operations = Vec::new();
+    let mut solde_cloture: Option<(NaiveDate, i64)> = None;
+
+    for record in reader.records() {
+        let record =
+            record.map_err(|e| ImportError::format(format.skip_lines + 1, e.to_string()))?;
+        let line = format.skip_lines + record.position().map_or(0, |p| p.line() as usize);
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let field = |index: usize| record.get(index).unwrap_or("").trim();
+        let parse_date = |index: usize| {
+            NaiveDate::parse_from_str(field(index), &format.date_format)
+                .map_err(|_| ImportError::format(line, format!("bad date '{}'", field(index))))
+        };
+        let parse_amount = |index: usize| -> Result<Option<i64>, ImportError> {
+            let value = field(index);
+            if value.is_empty() {
+                return Ok(None);
+            }
+            parse_decimal(value, format.decimal_comma)
+                .map(Some)
+                .ok_or_else(|| ImportError::format(line, format!("bad amount '{value}'")))
+        };
+
+        let date = parse_date(date)?;
+        let valeur = valeur.map(parse_date).transpose()?;
+        let nature = description
+            .iter()
+            .map(|index| field(*index))
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut montant = 0;
+        if let Some(index) = amount {
+            montant += parse_amount(index)?.unwrap_or(0);
+        }
+        if let Some(index) = debit {
+            montant -= parse_amount(index)?.unwrap_or(0).abs();
+        }
+        if let Some(index) = credit {
+            montant += parse_amount(index)?.unwrap_or(0).abs();
+        }
+
+        // Exports list operations newest first or oldest first: keep the
+        // balance of the first row with the latest date
+        if let Some(index) = balance
+            && let Some(value) = parse_amount(index)?
+            && solde_cloture.is_none_or(|(latest, _)| date > latest)
+        {
+            solde_cloture = Some((date, value));
+        }
+
+        operations.push(operation(date, valeur, nature, montant));
+    }
+
+    build_releve(operations, solde_cloture)
+}
+
+/// Import a CSV export downloaded from web banking.
+pub fn import_csv(path: &str, format: &CsvFormat) -> Result<Releve, ImportError> {
+    parse_csv(&read_file(path)?, format)
+}
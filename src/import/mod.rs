@@ -0,0 +1,13 @@
+pub mod camt053;
+pub mod common;
+pub mod csv;
+pub mod model;
+pub mod ofx;
+pub mod qif;
+
+pub use camt053::{import_camt053, parse_camt053};
+pub use common::ImportError;
+pub use csv::{import_csv, parse_csv};
+pub use model::{CsvColumn, CsvFormat, QifFormat};
+pub use ofx::{import_ofx, parse_ofx};
+pub use qif::{import_qif, parse_qif};
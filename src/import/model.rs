@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+/// A CSV column, by position (starting at 0) or by header name.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum CsvColumn {
+    Index(usize),
+    Name(String),
+}
+
+fn default_delimiter() -> char {
+    ';'
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_date_format() -> String {
+    "%d/%m/%Y".to_string()
+}
+
+/// How to read the CSV export of a bank.
+///
+/// Amounts are either in a single signed `amount` column, or in separate
+/// `debit` and `credit` columns.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CsvFormat {
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// Lines to skip before the header (account number, export date...)
+    #[serde(default)]
+    pub skip_lines: usize,
+    #[serde(default = "default_true")]
+    pub has_header: bool,
+    pub date: CsvColumn,
+    #[serde(default)]
+    pub valeur: Option<CsvColumn>,
+    /// Joined with a space to make `nature_des_operations`
+    pub description: Vec<CsvColumn>,
+    #[serde(default)]
+    pub amount: Option<CsvColumn>,
+    #[serde(default)]
+    pub debit: Option<CsvColumn>,
+    #[serde(default)]
+    pub credit: Option<CsvColumn>,
+    /// Balance after the operation, used for the soldes of the releve
+    #[serde(default)]
+    pub balance: Option<CsvColumn>,
+    /// chrono format of the dates
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    /// "1 234,56" rather than "1,234.56"
+    #[serde(default = "default_true")]
+    pub decimal_comma: bool,
+}
+
+impl Default for CsvFormat {
+    /// The CSV export of BNP Paribas web banking: one line with the account,
+    /// then "Date operation;Libelle court;Type operation;Libelle operation;Montant operation".
+    fn default() -> Self {
+        CsvFormat {
+            delimiter: ';',
+            skip_lines: 1,
+            has_header: true,
+            date: CsvColumn::Index(0),
+            valeur: None,
+            description: vec![CsvColumn::Index(3)],
+            amount: Some(CsvColumn::Index(4)),
+            debit: None,
+            credit: None,
+            balance: None,
+            date_format: default_date_format(),
+            decimal_comma: true,
+        }
+    }
+}
+
+/// How to read a QIF file, whose date and number formats depend on the bank.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QifFormat {
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    #[serde(default)]
+    pub decimal_comma: bool,
+}
+
+impl Default for QifFormat {
+    fn default() -> Self {
+        QifFormat {
+            date_format: default_date_format(),
+            decimal_comma: false,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use chrono::NaiveDate;
+use regex::Regex;
+
+use super::common::{ImportError, build_releve, operation, parse_decimal, read_file};
+use crate::parser::Releve;
+
+/// Leaf elements of an OFX aggregate, as (tag, value).
+///
+/// OFX 1.x is SGML where leaf elements are not closed (`<TRNAMT>-12.50`),
+/// OFX 2.x is XML (`<TRNAMT>-12.50</TRNAMT>`): only read up to the next tag.
+fn elements(aggregate: &str) -> Vec<(String, String)> {
+    let re = Regex::new(r"<([A-Za-z0-9.]+)>([^<\r\n]*)").unwrap();
+    re.captures_iter(aggregate)
+        .map(|caps| (caps[1].to_uppercase(), caps[2].trim().to_string()))
+        .filter(|(_, value)| !value.is_empty())
+        .collect()
+}
+
+fn element<'a>(elements: &'a [(String, String)], tag: &str) -> Option<&'a str> {
+    elements
+        .iter()
+        .find(|(t, _)| t == tag)
+        .map(|(_, v)| v.as_str())
+}
+
+/// "20240131", "20240131120000[+1:CET]"
+fn parse_ofx_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s.get(..8)?, "%Y%m%d").ok()
+}
+
+/// OFX amounts use a decimal point, some French banks write a comma.
+fn parse_ofx_amount(s: &str) -> Option<i64> {
+    parse_decimal(s, s.contains(',') && !s.contains('.'))
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+/// Parse the text of an OFX (or QFX) bank statement download.
+pub fn parse_ofx(text: &str) -> Result<Releve, ImportError> {
+    let transaction_re = Regex::new(r"(?is)<STMTTRN>(.*?)</STMTTRN>").unwrap();
+    let ledger_re = Regex::new(r"(?is)<LEDGERBAL>(.*?)</LEDGERBAL>").unwrap();
+
+    let mut operations = Vec::new();
+    for caps in transaction_re.captures_iter(text) {
+        let block = caps.get(1).unwrap();
+        let line = line_of(text, block.start());
+        let fields = elements(block.as_str());
+        let required = |tag: &str| {
+            element(&fields, tag)
+                .ok_or_else(|| ImportError::format(line, format!("STMTTRN without {tag}")))
+        };
+
+        let dtposted = required("DTPOSTED")?;
+        let date = parse_ofx_date(dtposted)
+            .ok_or_else(|| ImportError::format(line, format!("bad date '{dtposted}'")))?;
+        let valeur = element(&fields, "DTAVAIL").and_then(parse_ofx_date);
+        let trnamt = required("TRNAMT")?;
+        let montant = parse_ofx_amount(trnamt)
+            .ok_or_else(|| ImportError::format(line, format!("bad amount '{trnamt}'")))?;
+
+        let name = element(&fields, "NAME").unwrap_or("");
+        let memo = element(&fields, "MEMO").unwrap_or("");
+        let nature = if memo.is_empty() || name.contains(memo) {
+            name.to_string()
+        } else if name.is_empty() {
+            memo.to_string()
+        } else {
+            format!("{name} {memo}")
+        };
+
+        operations.push(operation(date, valeur, nature, montant));
+    }
+
+    let solde_cloture = ledger_re.captures(text).and_then(|caps| {
+        let fields = elements(&caps[1]);
+        let montant = parse_ofx_amount(element(&fields, "BALAMT")?)?;
+        let date = parse_ofx_date(element(&fields, "DTASOF")?)?;
+        Some((date, montant))
+    });
+
+    build_releve(operations, solde_cloture)
+}
+
+/// Import an OFX (or QFX) file downloaded from web banking.
+pub fn import_ofx(path: &str) -> Result<Releve, ImportError> {
+    parse_ofx(&read_file(path)?)
+}
@@ -0,0 +1,87 @@
+use chrono::NaiveDate;
+
+use super::common::{ImportError, build_releve, operation, parse_decimal, read_file};
+use super::model::QifFormat;
+use crate::parser::{Operation, Releve};
+
+/// A QIF record being read, with the line where it starts.
+#[derive(Default)]
+struct Record {
+    line: usize,
+    date: Option<String>,
+    amount: Option<String>,
+    payee: Option<String>,
+    memo: Option<String>,
+}
+
+impl Record {
+    fn into_operation(self, format: &QifFormat) -> Result<Operation, ImportError> {
+        let line = self.line;
+        let date = self
+            .date
+            .ok_or_else(|| ImportError::format(line, "record without date"))?;
+        let date = NaiveDate::parse_from_str(&date, &format.date_format)
+            .map_err(|_| ImportError::format(line, format!("bad date '{date}'")))?;
+        let amount = self
+            .amount
+            .ok_or_else(|| ImportError::format(line, "record without amount"))?;
+        let montant = parse_decimal(&amount, format.decimal_comma)
+            .ok_or_else(|| ImportError::format(line, format!("bad amount '{amount}'")))?;
+        let nature = [self.payee, self.memo]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(operation(date, None, nature, montant))
+    }
+}
+
+/// Parse the text of a QIF export (`!Type:Bank` or `!Type:CCard`).
+///
+/// Each record is a list of lines starting with a one letter code, ended by `^`;
+/// the `^` of the last record may be missing. Only the date (D), amount (T or
+/// U), payee (P) and memo (M) are used. A file with several accounts
+/// (`!Account` blocks) is rejected: export one account per file.
+pub fn parse_qif(text: &str, format: &QifFormat) -> Result<Releve, ImportError> {
+    let mut operations = Vec::new();
+    let mut record = Record::default();
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.to_ascii_lowercase().starts_with("!account") {
+            return Err(ImportError::format(
+                index + 1,
+                "several accounts in one QIF file, export one account per file",
+            ));
+        }
+        if line.is_empty() || line.starts_with('!') {
+            continue;
+        }
+        if record.line == 0 {
+            record.line = index + 1;
+        }
+        let mut chars = line.chars();
+        let code = chars.next();
+        let value = chars.as_str().trim().to_string();
+        match code {
+            Some('D') => record.date = Some(value),
+            Some('T' | 'U') => record.amount = Some(value),
+            Some('P') => record.payee = Some(value),
+            Some('M') => record.memo = Some(value),
+            Some('^') => operations.push(std::mem::take(&mut record).into_operation(format)?),
+            _ => {}
+        }
+    }
+    if record.line != 0 {
+        operations.push(record.into_operation(format)?);
+    }
+
+    // QIF has no closing balance
+    build_releve(operations, None)
+}
+
+/// Import a QIF file.
+pub fn import_qif(path: &str, format: &QifFormat) -> Result<Releve, ImportError> {
+    parse_qif(&read_file(path)?, format)
+}
@@ -1,4 +1,11 @@
+pub mod export;
+pub mod import;
 pub mod parser;
+pub mod store;
 pub mod ventilation;
 
-pub use parser::{Operation, Releve, Solde, SoldeType, compute_year, parse_pdf};
+pub use parser::{
+    ContinuityIssue, Operation, ParseError, ParserProfile, Provenance, Releve, Solde, SoldeType,
+    TextLine, check_continuity, compute_year, parse_lines, parse_lines_with_profile, parse_pdf,
+    parse_pdf_with_profile, parse_statement,
+};
@@ -0,0 +1,182 @@
+use chrono::{Datelike, NaiveDate};
+use std::fmt;
+
+use super::model::{Releve, Solde, SoldeType, euros};
+
+/// A problem found when chaining statements one after the other.
+#[derive(Debug, PartialEq)]
+pub enum ContinuityIssue {
+    /// solde_ouverture + credits - debits is not solde_cloture
+    InconsistentReleve {
+        date_du_releve: NaiveDate,
+        expected_cloture: i64,
+        actual_cloture: i64,
+        gap: i64,
+    },
+    /// The closing balance of a statement is not the opening balance of the next one
+    BalanceBreak {
+        previous: NaiveDate,
+        next: NaiveDate,
+        cloture: i64,
+        ouverture: i64,
+        gap: i64,
+    },
+    /// No statement for the months (year, month) between two statements
+    MissingMonths {
+        previous: NaiveDate,
+        next: NaiveDate,
+        months: Vec<(i32, u32)>,
+    },
+    /// Two statements cover the same month, or the next one has operations
+    /// dated on or before the closing date of the previous one
+    Overlap {
+        previous: NaiveDate,
+        next: NaiveDate,
+        operations: usize,
+    },
+}
+
+impl fmt::Display for ContinuityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContinuityIssue::InconsistentReleve {
+                date_du_releve,
+                expected_cloture,
+                actual_cloture,
+                gap,
+            } => write!(
+                f,
+                "Releve {date_du_releve}: solde ouverture + operations = {} but solde cloture = {} (gap {})",
+                euros(*expected_cloture),
+                euros(*actual_cloture),
+                euros(*gap)
+            ),
+            ContinuityIssue::BalanceBreak {
+                previous,
+                next,
+                cloture,
+                ouverture,
+                gap,
+            } => write!(
+                f,
+                "Balance break between {previous} and {next}: solde cloture {} but next solde ouverture {} (gap {})",
+                euros(*cloture),
+                euros(*ouverture),
+                euros(*gap)
+            ),
+            ContinuityIssue::MissingMonths {
+                previous,
+                next,
+                months,
+            } => {
+                let months: Vec<String> = months
+                    .iter()
+                    .map(|(year, month)| format!("{year}-{month:02}"))
+                    .collect();
+                write!(
+                    f,
+                    "Missing releve between {previous} and {next}: {}",
+                    months.join(", ")
+                )
+            }
+            ContinuityIssue::Overlap {
+                previous,
+                next,
+                operations,
+            } => write!(
+                f,
+                "Releves {previous} and {next} overlap ({operations} operations dated on or before {previous})"
+            ),
+        }
+    }
+}
+
+/// Balance as a signed amount: positive when creditor, negative when debtor.
+pub fn signed_solde(solde: &Solde) -> i64 {
+    match solde.solde_type {
+        SoldeType::Credit => solde.montant,
+        SoldeType::Debit => -solde.montant,
+    }
+}
+
+fn month_index(date: NaiveDate) -> i32 {
+    date.year() * 12 + date.month0() as i32
+}
+
+/// Check each statement balances, and that the statements follow each other
+/// month after month with matching closing and opening balances.
+/// Statements are taken in `date_du_releve` order whatever the order of `releves`.
+pub fn check_continuity(releves: &[Releve]) -> Vec<ContinuityIssue> {
+    let mut issues = Vec::new();
+
+    let mut sorted: Vec<&Releve> = releves.iter().collect();
+    sorted.sort_by_key(|r| r.date_du_releve);
+
+    for releve in &sorted {
+        let credits: i64 = releve
+            .operations
+            .iter()
+            .filter(|op| matches!(op.montant_type, SoldeType::Credit))
+            .map(|op| op.montant)
+            .sum();
+        let debits: i64 = releve
+            .operations
+            .iter()
+            .filter(|op| matches!(op.montant_type, SoldeType::Debit))
+            .map(|op| op.montant)
+            .sum();
+        let expected_cloture = signed_solde(&releve.solde_ouverture) + credits - debits;
+        let actual_cloture = signed_solde(&releve.solde_cloture);
+        if expected_cloture != actual_cloture {
+            issues.push(ContinuityIssue::InconsistentReleve {
+                date_du_releve: releve.date_du_releve,
+                expected_cloture,
+                actual_cloture,
+                gap: actual_cloture - expected_cloture,
+            });
+        }
+    }
+
+    for pair in sorted.windows(2) {
+        let (previous, next) = (pair[0], pair[1]);
+
+        let months_apart = month_index(next.date_du_releve) - month_index(previous.date_du_releve);
+        let overlapping = next
+            .operations
+            .iter()
+            .filter(|op| op.date <= previous.date_du_releve)
+            .count();
+        if months_apart == 0 || overlapping > 0 {
+            issues.push(ContinuityIssue::Overlap {
+                previous: previous.date_du_releve,
+                next: next.date_du_releve,
+                operations: overlapping,
+            });
+        }
+        if months_apart > 1 {
+            let months = (month_index(previous.date_du_releve) + 1
+                ..month_index(next.date_du_releve))
+                .map(|index| (index.div_euclid(12), index.rem_euclid(12) as u32 + 1))
+                .collect();
+            issues.push(ContinuityIssue::MissingMonths {
+                previous: previous.date_du_releve,
+                next: next.date_du_releve,
+                months,
+            });
+        }
+
+        let cloture = signed_solde(&previous.solde_cloture);
+        let ouverture = signed_solde(&next.solde_ouverture);
+        if cloture != ouverture {
+            issues.push(ContinuityIssue::BalanceBreak {
+                previous: previous.date_du_releve,
+                next: next.date_du_releve,
+                cloture,
+                ouverture,
+                gap: ouverture - cloture,
+            });
+        }
+    }
+
+    issues
+}
@@ -0,0 +1,137 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use std::sync::LazyLock;
+
+use super::model::{OperationDetails, OperationKind};
+
+/// A `&'static Regex` compiled the first time it is used
+macro_rules! regex {
+    ($re:literal) => {{
+        static RE: LazyLock<Regex> = LazyLock::new(|| Regex::new($re).unwrap());
+        &*RE
+    }};
+}
+
+fn capture(re: &Regex, text: &str) -> Option<String> {
+    re.captures(text)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str().trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// "120124" -> 2024-01-12
+fn parse_ddmmyy(s: &str) -> Option<NaiveDate> {
+    let day = s.get(0..2)?.parse().ok()?;
+    let month = s.get(2..4)?.parse().ok()?;
+    let year: i32 = s.get(4..6)?.parse().ok()?;
+    NaiveDate::from_ymd_opt(2000 + year, month, day)
+}
+
+/// Digits shown after the mask of a card number ("4974XXXXXXXX1234" -> "1234"),
+/// or the whole number when nothing follows the mask.
+fn card_suffix(card: &str) -> String {
+    capture(regex!(r"X+(\d+)$"), card).unwrap_or_else(|| card.to_string())
+}
+
+fn kind_of(nature: &str) -> OperationKind {
+    let starts = |prefixes: &[&str]| prefixes.iter().any(|p| nature.starts_with(p));
+    if nature.contains("RETRAIT DAB") {
+        OperationKind::RetraitDab
+    } else if starts(&[
+        "FACTURE(S) CARTE",
+        "FACTURE CARTE",
+        "FACTURES CARTE",
+        "CARTE X",
+    ]) {
+        OperationKind::CarteBancaire
+    } else if starts(&["PRLV SEPA", "PRELEVEMENT"]) {
+        OperationKind::PrelevementSepa
+    } else if starts(&[
+        "VIR SEPA RECU",
+        "VIREMENT SEPA RECU",
+        "VIR CPTE A CPTE RECU",
+        "VIR RECU",
+        "VIR INST RE",
+    ]) {
+        OperationKind::VirementRecu
+    } else if starts(&[
+        "VIR SEPA EMIS",
+        "VIREMENT SEPA EMIS",
+        "VIR CPTE A CPTE EMIS",
+        "VIRT CPTE A CPTE EMIS",
+        "VIR PERMANENT",
+        "VIR EUROPEEN EMIS",
+        "VIR INST EMIS",
+    ]) {
+        OperationKind::VirementEmis
+    } else if starts(&["REMISE CHEQUES", "REMISE CHEQUE"]) {
+        OperationKind::RemiseCheques
+    } else if starts(&["CHEQUE"]) {
+        OperationKind::Cheque
+    } else if starts(&["COMMISSION", "FRAIS", "COTISATION", "AGIOS"]) {
+        OperationKind::Commission
+    } else {
+        OperationKind::Autre
+    }
+}
+
+/// Decompose the free text of an operation into its kind and the fields
+/// printed by the bank for that kind. Both the BNP Paribas ("FACTURE(S) CARTE
+/// ... DU 120124 ...", "ID EMETTEUR/...") and Société Générale ("CARTE X1234
+/// 29/12 ...", "DE: ... ID: ...") wordings are recognized.
+pub fn decompose(nature_des_operations: &str) -> OperationDetails {
+    // Some descriptions start with the "*" flag of commissions
+    let nature = nature_des_operations.trim_start_matches(['*', ' ']);
+    let kind = kind_of(nature);
+
+    let mut details = OperationDetails {
+        kind: kind.clone(),
+        card_suffix: None,
+        purchase_date: None,
+        merchant: None,
+        creditor_id: None,
+        mandate_reference: None,
+        counterparty: None,
+    };
+
+    match kind {
+        OperationKind::CarteBancaire | OperationKind::RetraitDab => {
+            details.card_suffix =
+                capture(regex!(r"CARTE (\S*X\S*)"), nature).map(|c| card_suffix(&c));
+            let purchase_date = capture(regex!(r"\bDU (\d{6})\b"), nature);
+            details.purchase_date = purchase_date.as_deref().and_then(parse_ddmmyy);
+            if kind == OperationKind::CarteBancaire {
+                details.merchant = capture(regex!(r"\bDU \d{6} (.*)$"), nature)
+                    .or_else(|| capture(regex!(r"^CARTE \S+ \d{2}/\d{2} (.*)$"), nature));
+            }
+        }
+        OperationKind::PrelevementSepa => {
+            details.counterparty = capture(
+                regex!(r"\bDE: (.*?)(?: ID:| MOTIF:| REF:| MANDAT |$)"),
+                nature,
+            )
+            .or_else(|| {
+                capture(
+                    regex!(r"^(?:PRLV SEPA|PRELEVEMENT) (.*?)(?: ECH/| ID EMETTEUR/| MDT/| REF/| LIB/|$)"),
+                    nature,
+                )
+            });
+            details.creditor_id = capture(regex!(r"ID EMETTEUR/(\S+)"), nature)
+                .or_else(|| capture(regex!(r"\bID: (\S+)"), nature));
+            details.mandate_reference = capture(regex!(r"MDT/(\S+)"), nature)
+                .or_else(|| capture(regex!(r"\bMANDAT (\S+)"), nature));
+        }
+        OperationKind::VirementRecu => {
+            details.counterparty = capture(regex!(r"/DE (.*?)(?: /|$)"), nature)
+                .or_else(|| capture(regex!(r"\bDE: (.*?)(?: MOTIF:| REF:|$)"), nature));
+        }
+        OperationKind::VirementEmis => {
+            details.counterparty = capture(regex!(r"/BEN (.*?)(?: /|$)"), nature)
+                .or_else(|| capture(regex!(r"\bVERS (.*?)(?: /|$)"), nature))
+                .or_else(|| capture(regex!(r"\bPOUR: (.*?)(?: MOTIF:| REF:|$)"), nature));
+        }
+        _ => {}
+    }
+
+    details
+}
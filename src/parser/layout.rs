@@ -0,0 +1,220 @@
+use pdf_extract::{Document, MediaBox, OutputDev, OutputError, Transform, output_doc};
+
+use super::model::SoldeType;
+
+/// A line of text as laid out in the PDF, with the horizontal position of
+/// each character. Separators inserted by the extraction (spaces between
+/// words) have no position.
+#[derive(Debug, Clone, Default)]
+pub struct TextLine {
+    pub page: u32,
+    pub text: String,
+    pub xs: Vec<Option<f64>>,
+}
+
+impl TextLine {
+    /// Build a line without position information, e.g. from plain text.
+    pub fn plain(page: u32, text: &str) -> Self {
+        TextLine {
+            page,
+            text: text.to_string(),
+            xs: vec![None; text.chars().count()],
+        }
+    }
+
+    /// x position of the character starting at byte offset `offset` in `text`.
+    pub fn x_at(&self, offset: usize) -> Option<f64> {
+        let index = self.text.get(..offset)?.chars().count();
+        self.xs.get(index).copied().flatten()
+    }
+
+    /// x position of the last character before byte offset `end` in `text`.
+    pub fn last_x_before(&self, end: usize) -> Option<f64> {
+        let (offset, _) = self.text.get(..end)?.char_indices().next_back()?;
+        self.x_at(offset)
+    }
+
+    fn push(&mut self, s: &str, x: Option<f64>) {
+        for c in s.chars() {
+            self.text.push(c);
+            self.xs.push(x);
+        }
+    }
+}
+
+/// Join lines back into the text `pdf_extract::extract_text` would produce.
+pub fn lines_to_text(lines: &[TextLine]) -> String {
+    lines
+        .iter()
+        .map(|l| l.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Split plain text into lines without position information.
+pub fn lines_from_text(text: &str) -> Vec<TextLine> {
+    text.lines().map(|l| TextLine::plain(1, l)).collect()
+}
+
+/// Find the x position separating the Débit and Crédit columns, if `line` is
+/// the header of an operations table ("Date Nature des opérations Valeur Débit Crédit").
+pub fn credit_column_boundary(line: &TextLine) -> Option<f64> {
+    // The header letters are sometimes spread out ("D ate"), so match on the
+    // text with whitespace removed, keeping track of the original positions.
+    let (compact, xs): (String, Vec<Option<f64>>) = line
+        .text
+        .chars()
+        .zip(line.xs.iter())
+        .filter(|(c, _)| !c.is_whitespace())
+        .flat_map(|(c, x)| c.to_lowercase().map(move |lc| (lc, *x)))
+        .unzip();
+
+    let column_center = |words: &[&str]| -> Option<f64> {
+        words.iter().find_map(|word| {
+            let start = compact.find(word)?;
+            let first = compact[..start].chars().count();
+            let last = first + word.chars().count() - 1;
+            Some((xs.get(first).copied()?? + xs.get(last).copied()??) / 2.0)
+        })
+    };
+
+    let debit = column_center(&["débit", "debit"])?;
+    let credit = column_center(&["crédit", "credit"])?;
+    if credit <= debit {
+        return None;
+    }
+    Some((debit + credit) / 2.0)
+}
+
+/// Debit or credit, by the column of the amount printed at `amount_x`
+/// relative to the `credit_column_boundary` of the table; when either cannot
+/// be located, by the labels of credit operations `credit_keywords` found in
+/// the description.
+pub fn montant_type(
+    amount_x: Option<f64>,
+    column_boundary: Option<f64>,
+    nature: &str,
+    credit_keywords: &[&str],
+) -> SoldeType {
+    match (amount_x, column_boundary) {
+        (Some(x), Some(boundary)) if x > boundary => SoldeType::Credit,
+        (Some(_), Some(_)) => SoldeType::Debit,
+        _ if credit_keywords.iter().any(|k| nature.contains(k)) => SoldeType::Credit,
+        _ => SoldeType::Debit,
+    }
+}
+
+/// Same line and word splitting heuristics as `pdf_extract::PlainTextOutput`,
+/// but keeping the x position of every character.
+struct LineCollector {
+    lines: Vec<TextLine>,
+    current: TextLine,
+    page: u32,
+    last_end: f64,
+    last_y: f64,
+    first_char: bool,
+    page_height: f64,
+}
+
+impl LineCollector {
+    fn new() -> Self {
+        LineCollector {
+            lines: Vec::new(),
+            current: TextLine::default(),
+            page: 0,
+            last_end: 100000.,
+            last_y: 0.,
+            first_char: false,
+            page_height: 0.,
+        }
+    }
+
+    fn new_line(&mut self) {
+        let line = std::mem::take(&mut self.current);
+        self.lines.push(line);
+        self.current.page = self.page;
+    }
+
+    fn finish(mut self) -> Vec<TextLine> {
+        self.new_line();
+        self.lines
+    }
+}
+
+impl OutputDev for LineCollector {
+    fn begin_page(
+        &mut self,
+        page_num: u32,
+        media_box: &MediaBox,
+        _art_box: Option<(f64, f64, f64, f64)>,
+    ) -> Result<(), OutputError> {
+        self.page = page_num;
+        self.current.page = page_num;
+        self.page_height = media_box.ury - media_box.lly;
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn output_character(
+        &mut self,
+        trm: &Transform,
+        width: f64,
+        _spacing: f64,
+        font_size: f64,
+        char: &str,
+    ) -> Result<(), OutputError> {
+        // Flip the y axis so that y grows downwards, like PlainTextOutput does
+        let (x, y) = (trm.m31, self.page_height - trm.m32);
+        let font_x = font_size * (trm.m11 + trm.m21);
+        let font_y = font_size * (trm.m12 + trm.m22);
+        let transformed_font_size = (font_x * font_y).abs().sqrt();
+
+        if self.first_char {
+            if (y - self.last_y).abs() > transformed_font_size * 1.5 {
+                self.new_line();
+            }
+
+            // we've moved to the left and down
+            if x < self.last_end && (y - self.last_y).abs() > transformed_font_size * 0.5 {
+                self.new_line();
+            }
+
+            if x > self.last_end + transformed_font_size * 0.1 {
+                self.current.push(" ", None);
+            }
+        }
+
+        self.current.push(char, Some(x));
+        self.first_char = false;
+        self.last_y = y;
+        self.last_end = x + width * transformed_font_size;
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        self.first_char = true;
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+}
+
+/// Extract the lines of a PDF, keeping the page number and character positions.
+pub fn extract_lines(path: &str) -> Result<Vec<TextLine>, OutputError> {
+    let mut doc = Document::load(path)?;
+    if doc.is_encrypted() {
+        doc.decrypt("")?;
+    }
+    let mut collector = LineCollector::new();
+    output_doc(&doc, &mut collector)?;
+    Ok(collector.finish())
+}
@@ -1,5 +1,22 @@
+pub mod continuity;
+pub mod details;
+pub mod layout;
 pub mod model;
 pub mod parse;
+pub mod profile;
+pub mod societe_generale;
+pub mod statement_parser;
 
-pub use model::{Operation, Releve, Solde, SoldeType};
-pub use parse::{compute_year, parse_pdf};
+pub use continuity::{ContinuityIssue, check_continuity};
+pub use details::decompose;
+pub use layout::TextLine;
+pub use model::{Operation, OperationDetails, OperationKind, Releve, Solde, SoldeType};
+pub use parse::{
+    ParseError, Provenance, compute_year, parse_lines, parse_lines_with_profile, parse_pdf,
+    parse_pdf_with_profile,
+};
+pub use profile::{AccountHolder, ParserProfile, detect_account_holder};
+pub use statement_parser::{
+    BnpParibas, SocieteGenerale, StatementParser, detect_statement_parser, parse_statement,
+    statement_parsers,
+};
@@ -1,19 +1,75 @@
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SoldeType {
     Credit,
     Debit,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OperationKind {
+    CarteBancaire,
+    PrelevementSepa,
+    VirementEmis,
+    VirementRecu,
+    RetraitDab,
+    Cheque,
+    RemiseCheques,
+    Commission,
+    Autre,
+}
+
+/// Fields extracted from `nature_des_operations`, depending on the kind of operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationDetails {
+    pub kind: OperationKind,
+    /// Visible digits of the masked card number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub card_suffix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purchase_date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merchant: Option<String>,
+    /// SEPA creditor identifier (ID EMETTEUR)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creditor_id: Option<String>,
+    /// SEPA mandate reference (MDT)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mandate_reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counterparty: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Operation {
+    /// Stable identifier, see `Releve::assign_operation_ids`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub date: NaiveDate,
     pub nature_des_operations: String,
     pub valeur: NaiveDate,
     pub montant: i64,
     pub montant_type: SoldeType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<OperationDetails>,
+}
+
+/// A debit of 0 on 1970-01-01, to build operations with `..Default::default()`
+impl Default for Operation {
+    fn default() -> Self {
+        Operation {
+            id: None,
+            date: NaiveDate::default(),
+            nature_des_operations: String::new(),
+            valeur: NaiveDate::default(),
+            montant: 0,
+            montant_type: SoldeType::Debit,
+            details: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,4 +88,74 @@ pub struct Releve {
     pub check_debit: i64,
     pub check_credit: i64,
     pub operations: Vec<Operation>,
+    /// Account the statement belongs to (an IBAN or a name given on import)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+}
+
+/// Positions (releve, operation) of the operations already listed by an earlier
+/// releve of the same account, as when two statements or exports overlap: same
+/// date, valeur, signed amount and description, and same rank among the
+/// identical operations of their releve.
+pub fn repeated_operations(data: &[Releve]) -> HashSet<(usize, usize)> {
+    let mut releves: Vec<(usize, &Releve)> = data.iter().enumerate().collect();
+    releves.sort_by_key(|(r, releve)| (releve.date_du_releve, *r));
+    let mut seen: HashSet<(Option<&str>, String, usize)> = HashSet::new();
+    let mut repeated = HashSet::new();
+    for (r, releve) in releves {
+        let mut ranks: HashMap<String, usize> = HashMap::new();
+        let mut keys = Vec::new();
+        for (o, operation) in releve.operations.iter().enumerate() {
+            let signed = match operation.montant_type {
+                SoldeType::Debit => -operation.montant,
+                SoldeType::Credit => operation.montant,
+            };
+            let content = format!(
+                "{}|{}|{signed}|{}",
+                operation.date, operation.valeur, operation.nature_des_operations
+            );
+            let rank = ranks.entry(content.clone()).or_insert(0);
+            *rank += 1;
+            let key = (releve.account.as_deref(), content, *rank);
+            if seen.contains(&key) {
+                repeated.insert((r, o));
+            }
+            keys.push(key);
+        }
+        // Only the earlier releves count, not the identical operations of this one
+        seen.extend(keys);
+    }
+    repeated
+}
+
+/// An amount in centimes written in euros, as `-12.34`
+pub fn euros(centimes: i64) -> String {
+    format!("{:.2}", centimes as f64 / 100.0)
+}
+
+impl Releve {
+    /// Give each operation an identifier derived from the statement date, the
+    /// operation date, valeur, signed amount and description, and its rank among
+    /// the identical operations of the releve (two coffees on the same day).
+    /// Parsing the same statement again gives the same identifiers.
+    pub fn assign_operation_ids(&mut self) {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for operation in &mut self.operations {
+            let signed = match operation.montant_type {
+                SoldeType::Debit => -operation.montant,
+                SoldeType::Credit => operation.montant,
+            };
+            let content = format!(
+                "{}|{}|{}|{signed}|{}",
+                self.date_du_releve,
+                operation.date,
+                operation.valeur,
+                operation.nature_des_operations
+            );
+            let rank = seen.entry(content.clone()).or_insert(0);
+            let hash = format!("{:x}", Sha256::digest(format!("{content}|{rank}")));
+            *rank += 1;
+            operation.id = Some(hash[..16].to_string());
+        }
+    }
 }
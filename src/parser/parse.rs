@@ -1,10 +1,198 @@
 use chrono::NaiveDate;
-use pdf_extract::extract_text;
 use regex::Regex;
+use std::fmt;
 
+use super::details::decompose;
+use super::layout::{TextLine, credit_column_boundary, extract_lines, lines_to_text, montant_type};
 use super::model::{Operation, Releve, Solde, SoldeType};
+use super::profile::ParserProfile;
+use super::statement_parser::{BnpParibas, parse_statement};
+
+/// BNP Paribas labels of credit operations
+const CREDIT_KEYWORDS: &[&str] = &[
+    "VIR SEPA RECU",
+    "VIR CPTE A CPTE RECU",
+    "REJET RECU",
+    "RETROCESSION",
+    "REMISE CHEQUES",
+    "REMBOURST",
+];
+
+/// Where in the PDF a parse error was found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Provenance {
+    pub path: Option<String>,
+    pub page: Option<u32>,
+    pub line: Option<String>,
+}
 
-fn parse_amount(s: &str) -> Option<i64> {
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(path) = &self.path {
+            parts.push(path.clone());
+        }
+        if let Some(page) = self.page {
+            parts.push(format!("page {page}"));
+        }
+        if let Some(line) = &self.line {
+            parts.push(format!("line '{line}'"));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    Extraction {
+        provenance: Provenance,
+        message: String,
+    },
+    UnknownLayout {
+        provenance: Provenance,
+    },
+    MissingHeader {
+        provenance: Provenance,
+    },
+    MissingSoldes {
+        provenance: Provenance,
+    },
+    MissingTotals {
+        provenance: Provenance,
+    },
+    TotalsMismatch {
+        provenance: Provenance,
+        montant_type: SoldeType,
+        total: i64,
+        check: i64,
+    },
+    BadDate {
+        provenance: Provenance,
+        value: String,
+    },
+    BadAmount {
+        provenance: Provenance,
+        value: String,
+    },
+}
+
+impl ParseError {
+    pub fn provenance(&self) -> &Provenance {
+        match self {
+            ParseError::Extraction { provenance, .. }
+            | ParseError::UnknownLayout { provenance }
+            | ParseError::MissingHeader { provenance }
+            | ParseError::MissingSoldes { provenance }
+            | ParseError::MissingTotals { provenance }
+            | ParseError::TotalsMismatch { provenance, .. }
+            | ParseError::BadDate { provenance, .. }
+            | ParseError::BadAmount { provenance, .. } => provenance,
+        }
+    }
+
+    fn provenance_mut(&mut self) -> &mut Provenance {
+        match self {
+            ParseError::Extraction { provenance, .. }
+            | ParseError::UnknownLayout { provenance }
+            | ParseError::MissingHeader { provenance }
+            | ParseError::MissingSoldes { provenance }
+            | ParseError::MissingTotals { provenance }
+            | ParseError::TotalsMismatch { provenance, .. }
+            | ParseError::BadDate { provenance, .. }
+            | ParseError::BadAmount { provenance, .. } => provenance,
+        }
+    }
+
+    /// Record the PDF the error comes from.
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.provenance_mut().path = Some(path.to_string());
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Extraction { message, .. } => {
+                write!(f, "Error extracting text: {message}")?
+            }
+            ParseError::UnknownLayout { .. } => {
+                write!(f, "Statement layout not recognized by any parser")?
+            }
+            ParseError::MissingHeader { .. } => write!(f, "Could not find date pattern in releve")?,
+            ParseError::MissingSoldes { .. } => write!(f, "Could not find soldes")?,
+            ParseError::MissingTotals { .. } => {
+                write!(f, "Could not find the totals of the operations")?
+            }
+            ParseError::TotalsMismatch {
+                montant_type,
+                total,
+                check,
+                ..
+            } => match montant_type {
+                SoldeType::Debit => write!(
+                    f,
+                    "Debit mismatch: total_des_operations_debit={total} but check_debit={check}"
+                )?,
+                SoldeType::Credit => write!(
+                    f,
+                    "Credit mismatch: total_des_operations_credit={total} but check_credit={check}"
+                )?,
+            },
+            ParseError::BadDate { value, .. } => write!(f, "Invalid date: {value}")?,
+            ParseError::BadAmount { value, .. } => write!(f, "Invalid amount: {value}")?,
+        }
+        let provenance = self.provenance();
+        if *provenance != Provenance::default() {
+            write!(f, " ({provenance})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The extracted lines and their concatenated text, to trace a match in
+/// the text back to the page and line it comes from.
+pub(crate) struct Source<'a> {
+    pub(crate) lines: &'a [TextLine],
+    pub(crate) text: String,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> Source<'a> {
+    pub(crate) fn new(lines: &'a [TextLine]) -> Self {
+        let mut line_starts = Vec::with_capacity(lines.len());
+        let mut start = 0;
+        for line in lines {
+            line_starts.push(start);
+            start += line.text.len() + 1;
+        }
+        Source {
+            lines,
+            text: lines_to_text(lines),
+            line_starts,
+        }
+    }
+
+    pub(crate) fn at_line(&self, index: usize) -> Provenance {
+        self.lines
+            .get(index)
+            .map(|line| Provenance {
+                path: None,
+                page: Some(line.page),
+                line: Some(line.text.trim().to_string()),
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn at_offset(&self, offset: usize) -> Provenance {
+        let index = self.line_starts.partition_point(|&start| start <= offset);
+        self.at_line(index.saturating_sub(1))
+    }
+}
+
+pub(crate) fn parse_amount(s: &str) -> Option<i64> {
     if s.is_empty() {
         return None;
     }
@@ -18,21 +206,21 @@ fn parse_amount(s: &str) -> Option<i64> {
         .map(|v| (v * 100.0).round() as i64)
 }
 
-fn french_month_to_number(month: &str) -> Result<u32, String> {
+fn french_month_to_number(month: &str) -> Option<u32> {
     match month.to_lowercase().as_str() {
-        "janvier" => Ok(1),
-        "février" | "fevrier" => Ok(2),
-        "mars" => Ok(3),
-        "avril" => Ok(4),
-        "mai" => Ok(5),
-        "juin" => Ok(6),
-        "juillet" => Ok(7),
-        "août" | "aout" => Ok(8),
-        "septembre" => Ok(9),
-        "octobre" => Ok(10),
-        "novembre" => Ok(11),
-        "décembre" | "decembre" => Ok(12),
-        _ => Err(format!("Unknown month: {month}")),
+        "janvier" => Some(1),
+        "février" | "fevrier" => Some(2),
+        "mars" => Some(3),
+        "avril" => Some(4),
+        "mai" => Some(5),
+        "juin" => Some(6),
+        "juillet" => Some(7),
+        "août" | "aout" => Some(8),
+        "septembre" => Some(9),
+        "octobre" => Some(10),
+        "novembre" => Some(11),
+        "décembre" | "decembre" => Some(12),
+        _ => None,
     }
 }
 
@@ -42,30 +230,24 @@ struct ReleveDateInfo {
     year: i32,
 }
 
-fn parse_date_du_releve(text: &str) -> Result<ReleveDateInfo, String> {
+fn parse_date_du_releve(source: &Source) -> Result<ReleveDateInfo, ParseError> {
     let re = Regex::new(r"du \d+ \w+ \d+ au (\d+) (\w+) (\d+)").unwrap();
 
-    let caps = re
-        .captures(text)
-        .ok_or("Could not find date pattern in releve")?;
-
-    let day: u32 = caps
-        .get(1)
-        .ok_or("Missing day")?
-        .as_str()
-        .parse()
-        .map_err(|_| "Invalid day")?;
+    let caps = re.captures(&source.text).ok_or(ParseError::MissingHeader {
+        provenance: Provenance::default(),
+    })?;
 
-    let month_str = caps.get(2).ok_or("Missing month")?.as_str();
+    let whole = caps.get(0).unwrap();
+    let bad_date = || ParseError::BadDate {
+        provenance: source.at_offset(whole.start()),
+        value: whole.as_str().to_string(),
+    };
 
-    let year: i32 = caps
-        .get(3)
-        .ok_or("Missing year")?
-        .as_str()
-        .parse()
-        .map_err(|_| "Invalid year")?;
+    let day: u32 = caps[1].parse().map_err(|_| bad_date())?;
+    let month = french_month_to_number(&caps[2]).ok_or_else(bad_date)?;
+    let year: i32 = caps[3].parse().map_err(|_| bad_date())?;
 
-    let month = french_month_to_number(month_str)?;
+    NaiveDate::from_ymd_opt(year, month, day).ok_or_else(bad_date)?;
 
     Ok(ReleveDateInfo { day, month, year })
 }
@@ -80,98 +262,100 @@ pub fn compute_year(op_month: u32, releve_month: u32, releve_year: i32) -> i32 {
     }
 }
 
-fn parse_date_with_year(date_str: &str, releve: &ReleveDateInfo) -> Result<NaiveDate, String> {
+fn parse_date_with_year(date_str: &str, releve: &ReleveDateInfo) -> Option<NaiveDate> {
     // date_str is "DD.MM"
-    let parts: Vec<&str> = date_str.split('.').collect();
-    if parts.len() != 2 {
-        return Err(format!("Invalid date format: {date_str}"));
-    }
-
-    let day: u32 = parts[0]
-        .parse()
-        .map_err(|_| format!("Invalid day in date: {date_str}"))?;
-
-    let month: u32 = parts[1]
-        .parse()
-        .map_err(|_| format!("Invalid month in date: {date_str}"))?;
+    let (day, month) = date_str.split_once('.')?;
+    let day: u32 = day.parse().ok()?;
+    let month: u32 = month.parse().ok()?;
 
     let year = compute_year(month, releve.month, releve.year);
 
     NaiveDate::from_ymd_opt(year, month, day)
-        .ok_or_else(|| format!("Invalid date: {date_str} (year={year})"))
 }
 
-fn is_stop_line(line: &str, line_re: &Regex, line_re_no_text: &Regex) -> bool {
+fn is_stop_line(
+    line: &str,
+    line_re: &Regex,
+    line_re_no_text: &Regex,
+    profile: &ParserProfile,
+) -> bool {
     line_re.is_match(line)
         || line_re_no_text.is_match(line)
+        || profile.is_stop_line(line)
         || line.contains("BNP PARIBAS")
         || line.starts_with("P.")
-        || line.starts_with("504")
-        || line.starts_with("SCPT")
         || line.contains("RELEVE DE COMPTE")
         || line.contains("D ate")
-        || line.contains("GARENNE COL")
         || line.contains("RIB :")
-        || line.contains("M LAURENT")
-        || line.contains("APPARTEMENT")
         || line.contains("TOTAL DES OPERATIONS")
         || line.contains("SOLDE CREDITEUR")
         || line.contains("SOLDE DEBITEUR")
 }
 
-fn parse_soldes(text: &str) -> Result<(Solde, Solde), String> {
+fn parse_soldes(source: &Source) -> Result<(Solde, Solde), ParseError> {
     let re =
         Regex::new(r"SOLDE (CREDITEUR|DEBITEUR) AU \d{2}\.\d{2}\.\d{4}\s+([\d\s]+,\d{2})").unwrap();
 
-    let matches: Vec<_> = re.captures_iter(text).collect();
+    let matches: Vec<_> = re.captures_iter(&source.text).collect();
 
-    let first = matches.first().ok_or("Could not find solde ouverture")?;
-    let solde_type_ouverture = if first.get(1).ok_or("Missing solde type")?.as_str() == "CREDITEUR"
-    {
-        SoldeType::Credit
-    } else {
-        SoldeType::Debit
-    };
-    let montant_ouverture = parse_amount(first.get(2).ok_or("Missing montant")?.as_str())
-        .ok_or("Invalid montant for solde ouverture")?;
-    let ouverture = Solde {
-        solde_type: solde_type_ouverture,
-        montant: montant_ouverture,
+    let to_solde = |caps: &regex::Captures| -> Result<Solde, ParseError> {
+        let solde_type = if &caps[1] == "CREDITEUR" {
+            SoldeType::Credit
+        } else {
+            SoldeType::Debit
+        };
+        let montant_match = caps.get(2).unwrap();
+        let montant =
+            parse_amount(montant_match.as_str()).ok_or_else(|| ParseError::BadAmount {
+                provenance: source.at_offset(montant_match.start()),
+                value: montant_match.as_str().to_string(),
+            })?;
+        Ok(Solde {
+            solde_type,
+            montant,
+        })
     };
 
-    let last = matches.last().ok_or("Could not find solde cloture")?;
-    let solde_type_cloture = if last.get(1).ok_or("Missing solde type")?.as_str() == "CREDITEUR" {
-        SoldeType::Credit
-    } else {
-        SoldeType::Debit
-    };
-    let montant_cloture = parse_amount(last.get(2).ok_or("Missing montant")?.as_str())
-        .ok_or("Invalid montant for solde cloture")?;
-    let cloture = Solde {
-        solde_type: solde_type_cloture,
-        montant: montant_cloture,
+    let (first, last) = match (matches.first(), matches.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            return Err(ParseError::MissingSoldes {
+                provenance: Provenance::default(),
+            });
+        }
     };
 
-    Ok((ouverture, cloture))
+    Ok((to_solde(first)?, to_solde(last)?))
 }
 
-fn parse_total_des_operations(text: &str) -> Result<(i64, i64), String> {
+/// Returns the debit and credit totals, and where the totals line was found.
+fn parse_total_des_operations(source: &Source) -> Result<(i64, i64, Provenance), ParseError> {
     let re = Regex::new(r"TOTAL DES OPERATIONS\s+([\d\s]+,\d{2})\s+([\d\s]+,\d{2})").unwrap();
 
-    let caps = re
-        .captures(text)
-        .ok_or("Could not find TOTAL DES OPERATIONS")?;
+    let caps = re.captures(&source.text).ok_or(ParseError::MissingTotals {
+        provenance: Provenance::default(),
+    })?;
 
-    let debit = parse_amount(caps.get(1).ok_or("Missing debit total")?.as_str())
-        .ok_or("Invalid debit total")?;
+    let provenance = source.at_offset(caps.get(0).unwrap().start());
+    let amount = |index: usize| {
+        parse_amount(&caps[index]).ok_or_else(|| ParseError::BadAmount {
+            provenance: provenance.clone(),
+            value: caps[index].to_string(),
+        })
+    };
 
-    let credit = parse_amount(caps.get(2).ok_or("Missing credit total")?.as_str())
-        .ok_or("Invalid credit total")?;
+    let debit = amount(1)?;
+    let credit = amount(2)?;
 
-    Ok((debit, credit))
+    Ok((debit, credit, provenance))
 }
 
-fn parse_operations(text: &str, releve: &ReleveDateInfo) -> Vec<Operation> {
+fn parse_operations(
+    source: &Source,
+    releve: &ReleveDateInfo,
+    profile: &ParserProfile,
+) -> Result<Vec<Operation>, ParseError> {
+    let lines = source.lines;
     let mut operations = Vec::new();
 
     // Pattern with text on same line: "03.01 03.01 1,50* COMMISSIONS..."
@@ -181,11 +365,13 @@ fn parse_operations(text: &str, releve: &ReleveDateInfo) -> Vec<Operation> {
     let line_re_no_text =
         Regex::new(r"^(\d{2}\.\d{2})\s+(\d{2}\.\d{2})\s+([\d\s]+,\d{2})$").unwrap();
 
-    let lines: Vec<&str> = text.lines().collect();
+    let mut column_boundary: Option<f64> = None;
 
     let mut i = 0;
     while i < lines.len() {
-        let line = lines[i].trim();
+        let text_line = &lines[i];
+        let leading = text_line.text.len() - text_line.text.trim_start().len();
+        let line = text_line.text.trim();
 
         if line.is_empty() {
             i += 1;
@@ -193,52 +379,53 @@ fn parse_operations(text: &str, releve: &ReleveDateInfo) -> Vec<Operation> {
         }
 
         // Try pattern with text on same line first
-        let (date_raw, valeur_raw, amount_str, mut nature) =
-            if let Some(caps) = line_re.captures(line) {
-                (
-                    caps.get(1).unwrap().as_str(),
-                    caps.get(2).unwrap().as_str(),
-                    caps.get(3).unwrap().as_str().trim(),
-                    caps.get(4).unwrap().as_str().trim().to_string(),
-                )
-            } else if let Some(caps) = line_re_no_text.captures(line) {
-                // Text starts on next line
-                (
-                    caps.get(1).unwrap().as_str(),
-                    caps.get(2).unwrap().as_str(),
-                    caps.get(3).unwrap().as_str().trim(),
-                    String::new(),
-                )
-            } else {
-                i += 1;
-                continue;
-            };
-
-        let date = match parse_date_with_year(date_raw, releve) {
-            Ok(d) => d,
-            Err(_) => {
+        let caps = match line_re
+            .captures(line)
+            .or_else(|| line_re_no_text.captures(line))
+        {
+            Some(caps) => caps,
+            None => {
+                if let Some(boundary) = credit_column_boundary(text_line) {
+                    column_boundary = Some(boundary);
+                }
                 i += 1;
                 continue;
             }
         };
-        let valeur = match parse_date_with_year(valeur_raw, releve) {
-            Ok(d) => d,
-            Err(_) => {
-                i += 1;
-                continue;
-            }
+        let date_raw = caps.get(1).unwrap().as_str();
+        let valeur_raw = caps.get(2).unwrap().as_str();
+        let amount_match = caps.get(3).unwrap();
+        let amount_str = amount_match.as_str().trim();
+        // Text starts on next line when the line has no text part
+        let mut nature = caps
+            .get(4)
+            .map(|m| m.as_str().trim().to_string())
+            .unwrap_or_default();
+
+        // Position of the last digit of the amount: amounts are right-aligned
+        // in the Débit and Crédit columns.
+        let amount_end = amount_match.start() + amount_match.as_str().trim_end().len();
+        let amount_x = text_line.last_x_before(leading + amount_end);
+
+        let operation_line = i;
+        let bad_date = |value: &str| ParseError::BadDate {
+            provenance: source.at_line(operation_line),
+            value: value.to_string(),
         };
+        let date = parse_date_with_year(date_raw, releve).ok_or_else(|| bad_date(date_raw))?;
+        let valeur =
+            parse_date_with_year(valeur_raw, releve).ok_or_else(|| bad_date(valeur_raw))?;
 
         i += 1;
         while i < lines.len() {
-            let next_line = lines[i].trim();
+            let next_line = lines[i].text.trim();
 
             if next_line.is_empty() {
                 i += 1;
                 continue;
             }
 
-            if is_stop_line(next_line, &line_re, &line_re_no_text) {
+            if is_stop_line(next_line, &line_re, &line_re_no_text, profile) {
                 break;
             }
 
@@ -249,48 +436,96 @@ fn parse_operations(text: &str, releve: &ReleveDateInfo) -> Vec<Operation> {
             i += 1;
         }
 
-        let montant = parse_amount(amount_str).unwrap_or(0);
+        let montant = parse_amount(amount_str).ok_or_else(|| ParseError::BadAmount {
+            provenance: source.at_line(operation_line),
+            value: amount_str.to_string(),
+        })?;
 
-        let montant_type = if nature.contains("VIR SEPA RECU")
-            || nature.contains("VIR CPTE A CPTE RECU")
-            || nature.contains("REJET RECU")
-            || nature.contains("RETROCESSION")
-            || nature.contains("REMISE CHEQUES")
-            || nature.contains("REMBOURST")
-        {
-            SoldeType::Credit
-        } else {
-            SoldeType::Debit
-        };
+        let montant_type = montant_type(amount_x, column_boundary, &nature, CREDIT_KEYWORDS);
 
+        let nature_des_operations = nature.trim().to_string();
         operations.push(Operation {
+            id: None,
             date,
-            nature_des_operations: nature.trim().to_string(),
+            details: Some(decompose(&nature_des_operations)),
+            nature_des_operations,
             valeur,
             montant,
             montant_type,
         });
-
-        continue;
     }
 
-    operations
+    Ok(operations)
+}
+
+pub fn parse_pdf(path: &str) -> Result<Releve, ParseError> {
+    parse_pdf_with_profile(path, &ParserProfile::default())
+}
+
+pub fn parse_pdf_with_profile(path: &str, profile: &ParserProfile) -> Result<Releve, ParseError> {
+    let lines = extract_lines(path).map_err(|e| ParseError::Extraction {
+        provenance: Provenance {
+            path: Some(path.to_string()),
+            ..Default::default()
+        },
+        message: e.to_string(),
+    })?;
+    parse_statement(&lines, profile).map_err(|e| e.with_path(path))
+}
+
+/// "05.01 05.01 ...": the operation and value dates of a BNP Paribas operation
+pub(crate) fn is_operation_line(line: &str) -> bool {
+    let re = Regex::new(r"^\d{2}\.\d{2}\s+\d{2}\.\d{2}\s").unwrap();
+    re.is_match(line)
+}
+
+/// Parse a BNP Paribas statement from its extracted lines. Character
+/// positions, when present, are used to tell debits from credits.
+pub fn parse_lines(lines: &[TextLine]) -> Result<Releve, ParseError> {
+    parse_lines_with_profile(lines, &ParserProfile::default())
 }
 
-pub fn parse_pdf(path: &str) -> Result<Releve, String> {
-    let text = extract_text(path).map_err(|e| format!("Error extracting text: {e}"))?;
+pub fn parse_lines_with_profile(
+    lines: &[TextLine],
+    profile: &ParserProfile,
+) -> Result<Releve, ParseError> {
+    let source = Source::new(lines);
+    let profile = profile.for_statement(lines, &BnpParibas);
 
-    let releve_info = parse_date_du_releve(&text)?;
+    let releve_info = parse_date_du_releve(&source)?;
 
+    // Already validated by parse_date_du_releve
     let date_du_releve =
-        NaiveDate::from_ymd_opt(releve_info.year, releve_info.month, releve_info.day)
-            .ok_or("Invalid date du releve")?;
+        NaiveDate::from_ymd_opt(releve_info.year, releve_info.month, releve_info.day).unwrap();
 
-    let (solde_ouverture, solde_cloture) = parse_soldes(&text)?;
-    let (total_des_operations_debit, total_des_operations_credit) =
-        parse_total_des_operations(&text)?;
-    let operations = parse_operations(&text, &releve_info);
+    let (solde_ouverture, solde_cloture) = parse_soldes(&source)?;
+    let (total_des_operations_debit, total_des_operations_credit, totals_provenance) =
+        parse_total_des_operations(&source)?;
+    let operations = parse_operations(&source, &releve_info, &profile)?;
+
+    build_releve(
+        date_du_releve,
+        (solde_ouverture, solde_cloture),
+        (
+            total_des_operations_debit,
+            total_des_operations_credit,
+            totals_provenance,
+        ),
+        operations,
+    )
+}
 
+/// Assemble a releve, checking the operations add up to the totals printed on the statement.
+pub(crate) fn build_releve(
+    date_du_releve: NaiveDate,
+    (solde_ouverture, solde_cloture): (Solde, Solde),
+    (total_des_operations_debit, total_des_operations_credit, totals_provenance): (
+        i64,
+        i64,
+        Provenance,
+    ),
+    operations: Vec<Operation>,
+) -> Result<Releve, ParseError> {
     let check_debit: i64 = operations
         .iter()
         .filter(|op| matches!(op.montant_type, SoldeType::Debit))
@@ -304,18 +539,24 @@ pub fn parse_pdf(path: &str) -> Result<Releve, String> {
         .sum();
 
     if total_des_operations_debit != check_debit {
-        return Err(format!(
-            "Debit mismatch: total_des_operations_debit={total_des_operations_debit} but check_debit={check_debit}"
-        ));
+        return Err(ParseError::TotalsMismatch {
+            provenance: totals_provenance,
+            montant_type: SoldeType::Debit,
+            total: total_des_operations_debit,
+            check: check_debit,
+        });
     }
 
     if total_des_operations_credit != check_credit {
-        return Err(format!(
-            "Credit mismatch: total_des_operations_credit={total_des_operations_credit} but check_credit={check_credit}"
-        ));
+        return Err(ParseError::TotalsMismatch {
+            provenance: totals_provenance,
+            montant_type: SoldeType::Credit,
+            total: total_des_operations_credit,
+            check: check_credit,
+        });
     }
 
-    Ok(Releve {
+    let mut releve = Releve {
         date_du_releve,
         solde_ouverture,
         solde_cloture,
@@ -324,5 +565,8 @@ pub fn parse_pdf(path: &str) -> Result<Releve, String> {
         check_debit,
         check_credit,
         operations,
-    })
+        account: None,
+    };
+    releve.assign_operation_ids();
+    Ok(releve)
 }
@@ -0,0 +1,104 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::layout::TextLine;
+use super::statement_parser::StatementParser;
+
+fn default_true() -> bool {
+    true
+}
+
+/// Settings specific to an account holder, loaded from a YAML file.
+///
+/// The holder name and address are printed at the top of every page, and
+/// would otherwise be appended to the description of the operation before
+/// a page break.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ParserProfile {
+    /// Lines containing one of these strings end an operation description
+    #[serde(default)]
+    pub stop_contains: Vec<String>,
+    /// Lines starting with one of these strings end an operation description
+    #[serde(default)]
+    pub stop_starts_with: Vec<String>,
+    /// Add the holder name and address found on the first page to `stop_contains`
+    #[serde(default = "default_true")]
+    pub detect_account_holder: bool,
+}
+
+impl Default for ParserProfile {
+    fn default() -> Self {
+        ParserProfile {
+            stop_contains: Vec::new(),
+            stop_starts_with: Vec::new(),
+            detect_account_holder: true,
+        }
+    }
+}
+
+impl ParserProfile {
+    pub fn is_stop_line(&self, line: &str) -> bool {
+        self.stop_contains.iter().any(|s| line.contains(s.as_str()))
+            || self
+                .stop_starts_with
+                .iter()
+                .any(|s| line.starts_with(s.as_str()))
+    }
+
+    /// The profile completed with the account holder found in `lines`, if
+    /// enabled, `parser` being the layout of the statement.
+    pub fn for_statement(&self, lines: &[TextLine], parser: &dyn StatementParser) -> ParserProfile {
+        let mut profile = self.clone();
+        if self.detect_account_holder
+            && let Some(holder) = detect_account_holder(lines, parser)
+        {
+            profile.stop_contains.push(holder.name);
+            profile.stop_contains.extend(holder.address);
+        }
+        profile
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountHolder {
+    pub name: String,
+    pub address: Vec<String>,
+}
+
+/// Find the holder name ("M JEAN DUPONT", "MME ...", "M OU MME ...") and the
+/// address lines below it, up to the line with the postal code. Only the
+/// header is searched, up to the first operation line of `parser`.
+pub fn detect_account_holder(
+    lines: &[TextLine],
+    parser: &dyn StatementParser,
+) -> Option<AccountHolder> {
+    let name_re =
+        Regex::new(r"^(M|MME|MLLE|MR|MONSIEUR|MADAME)( (OU|ET) (M|MME|MLLE))? [A-Z][A-Z' -]+$")
+            .unwrap();
+    let postal_code_re = Regex::new(r"^\d{5} ").unwrap();
+
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|l| l.text.trim())
+        .filter(|l| !l.is_empty())
+        .take_while(|l| !parser.is_operation_line(l))
+        .collect();
+
+    let start = lines.iter().position(|l| name_re.is_match(l))?;
+    let mut address = Vec::new();
+    for line in lines.iter().skip(start + 1).take(4) {
+        address.push(line.to_string());
+        if postal_code_re.is_match(line) {
+            return Some(AccountHolder {
+                name: lines[start].to_string(),
+                address,
+            });
+        }
+    }
+
+    // Without a postal code we cannot tell where the address ends
+    Some(AccountHolder {
+        name: lines[start].to_string(),
+        address: Vec::new(),
+    })
+}
@@ -0,0 +1,223 @@
+use chrono::NaiveDate;
+use regex::Regex;
+
+use super::details::decompose;
+use super::layout::{TextLine, credit_column_boundary, montant_type};
+use super::model::{Operation, Releve, Solde, SoldeType};
+use super::parse::{ParseError, Provenance, Source, build_releve, parse_amount};
+use super::profile::ParserProfile;
+use super::statement_parser::SocieteGenerale;
+
+/// Société Générale labels of credit operations
+const CREDIT_KEYWORDS: &[&str] = &[
+    "VIR RECU",
+    "VIR INST RE",
+    "VIR SEPA RECU",
+    "REMISE CHEQUE",
+    "REMBOURSEMENT",
+    "AVOIR",
+];
+
+/// Amounts are printed "1 234,56" or "1.234,56", with an optional sign on balances.
+fn parse_sg_amount(s: &str) -> Option<i64> {
+    let negative = s.trim_start().starts_with('-');
+    let cleaned: String = s
+        .chars()
+        .filter(|c| *c != '.' && *c != '+' && *c != '-')
+        .collect();
+    parse_amount(&cleaned).map(|v| if negative { -v } else { v })
+}
+
+/// "31/01/2024"
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%d/%m/%Y").ok()
+}
+
+fn is_stop_line(line: &str, profile: &ParserProfile) -> bool {
+    let upper = line.to_uppercase();
+    profile.is_stop_line(line)
+        || upper.contains("SOCIETE GENERALE")
+        || upper.contains("SOCIÉTÉ GÉNÉRALE")
+        || upper.contains("RELEVÉ DES OPÉRATIONS")
+        || upper.contains("RELEVE DES OPERATIONS")
+        || upper.contains("NATURE DE L'OPÉRATION")
+        || upper.contains("NATURE DE L'OPERATION")
+        || upper.contains("TOTAUX DES MOUVEMENTS")
+        || upper.contains("NOUVEAU SOLDE")
+        || upper.contains("SOLDE PRÉCÉDENT")
+        || upper.contains("SOLDE PRECEDENT")
+        || upper.starts_with("PAGE ")
+        || upper.contains("SUITE >>>")
+}
+
+/// "31/01/2024 31/01/2024 ...": the operation and value dates of an operation
+pub(crate) fn is_operation_line(line: &str) -> bool {
+    let re = Regex::new(r"^\d{2}/\d{2}/\d{4}\s+\d{2}/\d{2}/\d{4}\s").unwrap();
+    re.is_match(line)
+}
+
+/// An operation whose description may continue on the next lines, with the
+/// x position of its amount.
+type PendingOperation = (Operation, Option<f64>);
+
+fn finish_operation(
+    current: &mut Option<PendingOperation>,
+    column_boundary: Option<f64>,
+    operations: &mut Vec<Operation>,
+) {
+    if let Some((mut operation, amount_x)) = current.take() {
+        operation.montant_type = montant_type(
+            amount_x,
+            column_boundary,
+            &operation.nature_des_operations,
+            CREDIT_KEYWORDS,
+        );
+        operation.details = Some(decompose(&operation.nature_des_operations));
+        operations.push(operation);
+    }
+}
+
+/// Parse a Société Générale statement ("Relevé des opérations"): dates are
+/// printed DD/MM/YYYY and the amount comes last on the operation line.
+pub fn parse_lines_with_profile(
+    lines: &[TextLine],
+    profile: &ParserProfile,
+) -> Result<Releve, ParseError> {
+    let source = Source::new(lines);
+    let profile = profile.for_statement(lines, &SocieteGenerale);
+
+    let period_re = Regex::new(r"(?i)du \d{2}/\d{2}/\d{4} au (\d{2}/\d{2}/\d{4})").unwrap();
+    let solde_re = Regex::new(
+        r"(?i)^(SOLDE PR[ÉE]C[ÉE]DENT|NOUVEAU SOLDE) AU \d{2}/\d{2}/\d{4}\s+([+-]?\s*[\d .]+,\d{2})$",
+    )
+    .unwrap();
+    let totals_re =
+        Regex::new(r"(?i)^TOTAUX DES MOUVEMENTS\s+([\d .]+,\d{2})\s+([\d .]+,\d{2})$").unwrap();
+    let operation_re = Regex::new(
+        r"^(\d{2}/\d{2}/\d{4})\s+(\d{2}/\d{2}/\d{4})\s+(.+?)\s+(\d{1,3}(?:[ .]\d{3})*,\d{2})$",
+    )
+    .unwrap();
+
+    let mut date_du_releve: Option<NaiveDate> = None;
+    let mut solde_ouverture: Option<Solde> = None;
+    let mut solde_cloture: Option<Solde> = None;
+    let mut totals: Option<(i64, i64, Provenance)> = None;
+    let mut operations: Vec<Operation> = Vec::new();
+    let mut column_boundary: Option<f64> = None;
+    let mut current: Option<PendingOperation> = None;
+
+    for (index, text_line) in lines.iter().enumerate() {
+        let leading = text_line.text.len() - text_line.text.trim_start().len();
+        let line = text_line.text.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(caps) = operation_re.captures(line) {
+            finish_operation(&mut current, column_boundary, &mut operations);
+            let bad_date = |value: &str| ParseError::BadDate {
+                provenance: source.at_line(index),
+                value: value.to_string(),
+            };
+            let date = parse_date(&caps[1]).ok_or_else(|| bad_date(&caps[1]))?;
+            let valeur = parse_date(&caps[2]).ok_or_else(|| bad_date(&caps[2]))?;
+            let amount = caps.get(4).unwrap();
+            let montant =
+                parse_sg_amount(amount.as_str()).ok_or_else(|| ParseError::BadAmount {
+                    provenance: source.at_line(index),
+                    value: amount.as_str().to_string(),
+                })?;
+            let amount_x = text_line.last_x_before(leading + amount.end());
+            current = Some((
+                Operation {
+                    id: None,
+                    date,
+                    nature_des_operations: caps[3].trim().to_string(),
+                    valeur,
+                    montant,
+                    montant_type: SoldeType::Debit,
+                    details: None,
+                },
+                amount_x,
+            ));
+            continue;
+        }
+
+        let mut stop = is_stop_line(line, &profile);
+        if let Some(caps) = solde_re.captures(line) {
+            let amount = caps.get(2).unwrap();
+            let montant =
+                parse_sg_amount(amount.as_str()).ok_or_else(|| ParseError::BadAmount {
+                    provenance: source.at_line(index),
+                    value: amount.as_str().to_string(),
+                })?;
+            let amount_x = text_line.last_x_before(leading + amount.end());
+            let solde_type = match (amount_x, column_boundary) {
+                (Some(x), Some(boundary)) if x <= boundary => SoldeType::Debit,
+                _ if montant < 0 => SoldeType::Debit,
+                _ => SoldeType::Credit,
+            };
+            let solde = Solde {
+                solde_type,
+                montant: montant.abs(),
+            };
+            if caps[1].to_uppercase().starts_with("NOUVEAU") {
+                solde_cloture = Some(solde);
+            } else if solde_ouverture.is_none() {
+                solde_ouverture = Some(solde);
+            }
+            stop = true;
+        } else if let Some(caps) = totals_re.captures(line) {
+            let provenance = source.at_line(index);
+            let amount = |value: &str| {
+                parse_sg_amount(value).ok_or_else(|| ParseError::BadAmount {
+                    provenance: provenance.clone(),
+                    value: value.to_string(),
+                })
+            };
+            totals = Some((amount(&caps[1])?, amount(&caps[2])?, provenance.clone()));
+            stop = true;
+        } else if let Some(boundary) = credit_column_boundary(text_line) {
+            column_boundary = Some(boundary);
+            stop = true;
+        } else if let Some(caps) = period_re.captures(line) {
+            if date_du_releve.is_none() {
+                date_du_releve = Some(parse_date(&caps[1]).ok_or_else(|| ParseError::BadDate {
+                    provenance: source.at_line(index),
+                    value: caps[1].to_string(),
+                })?);
+            }
+            stop = true;
+        }
+
+        if stop {
+            finish_operation(&mut current, column_boundary, &mut operations);
+        } else if let Some((operation, _)) = current.as_mut() {
+            operation.nature_des_operations.push(' ');
+            operation.nature_des_operations.push_str(line);
+        }
+    }
+    finish_operation(&mut current, column_boundary, &mut operations);
+
+    let date_du_releve = date_du_releve.ok_or(ParseError::MissingHeader {
+        provenance: Provenance::default(),
+    })?;
+    let (solde_ouverture, solde_cloture) = match (solde_ouverture, solde_cloture) {
+        (Some(ouverture), Some(cloture)) => (ouverture, cloture),
+        _ => {
+            return Err(ParseError::MissingSoldes {
+                provenance: Provenance::default(),
+            });
+        }
+    };
+    let totals = totals.ok_or(ParseError::MissingTotals {
+        provenance: Provenance::default(),
+    })?;
+
+    build_releve(
+        date_du_releve,
+        (solde_ouverture, solde_cloture),
+        totals,
+        operations,
+    )
+}
@@ -0,0 +1,107 @@
+use regex::Regex;
+
+use super::layout::{TextLine, lines_to_text};
+use super::model::Releve;
+use super::parse::{ParseError, Provenance};
+use super::profile::ParserProfile;
+use super::{parse, societe_generale};
+
+/// A bank statement layout.
+pub trait StatementParser {
+    /// Name of the bank, as shown to the user
+    fn name(&self) -> &'static str;
+    /// Confidence, between 0 and 1, that `lines` are a statement in this layout
+    fn detect(&self, lines: &[TextLine]) -> f64;
+    fn parse(&self, lines: &[TextLine], profile: &ParserProfile) -> Result<Releve, ParseError>;
+    /// Whether `line` starts an operation: the header of the statement ends
+    /// at the first one
+    fn is_operation_line(&self, line: &str) -> bool;
+}
+
+pub struct BnpParibas;
+
+impl StatementParser for BnpParibas {
+    fn name(&self) -> &'static str {
+        "BNP Paribas"
+    }
+
+    fn detect(&self, lines: &[TextLine]) -> f64 {
+        let text = lines_to_text(lines);
+        let solde_re = Regex::new(r"SOLDE (CREDITEUR|DEBITEUR) AU \d{2}\.\d{2}\.\d{4}").unwrap();
+        let mut confidence = 0.0;
+        if text.contains("BNP PARIBAS") {
+            confidence += 0.6;
+        }
+        if text.contains("TOTAL DES OPERATIONS") {
+            confidence += 0.2;
+        }
+        if solde_re.is_match(&text) {
+            confidence += 0.2;
+        }
+        confidence
+    }
+
+    fn parse(&self, lines: &[TextLine], profile: &ParserProfile) -> Result<Releve, ParseError> {
+        parse::parse_lines_with_profile(lines, profile)
+    }
+
+    fn is_operation_line(&self, line: &str) -> bool {
+        parse::is_operation_line(line)
+    }
+}
+
+pub struct SocieteGenerale;
+
+impl StatementParser for SocieteGenerale {
+    fn name(&self) -> &'static str {
+        "Société Générale"
+    }
+
+    fn detect(&self, lines: &[TextLine]) -> f64 {
+        let text = lines_to_text(lines).to_uppercase();
+        let mut confidence = 0.0;
+        if text.contains("SOCIETE GENERALE") || text.contains("SOCIÉTÉ GÉNÉRALE") {
+            confidence += 0.6;
+        }
+        if text.contains("TOTAUX DES MOUVEMENTS") {
+            confidence += 0.2;
+        }
+        if text.contains("NOUVEAU SOLDE AU") {
+            confidence += 0.2;
+        }
+        confidence
+    }
+
+    fn parse(&self, lines: &[TextLine], profile: &ParserProfile) -> Result<Releve, ParseError> {
+        societe_generale::parse_lines_with_profile(lines, profile)
+    }
+
+    fn is_operation_line(&self, line: &str) -> bool {
+        societe_generale::is_operation_line(line)
+    }
+}
+
+/// All the known statement layouts.
+pub fn statement_parsers() -> Vec<Box<dyn StatementParser>> {
+    vec![Box::new(BnpParibas), Box::new(SocieteGenerale)]
+}
+
+/// The parser most confident it can read `lines`, if any recognizes them.
+pub fn detect_statement_parser(lines: &[TextLine]) -> Option<Box<dyn StatementParser>> {
+    // Reversed so that, on a tie, the first parser of the list wins
+    statement_parsers()
+        .into_iter()
+        .rev()
+        .map(|parser| (parser.detect(lines), parser))
+        .filter(|(confidence, _)| *confidence > 0.0)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, parser)| parser)
+}
+
+/// Parse a statement of any known layout.
+pub fn parse_statement(lines: &[TextLine], profile: &ParserProfile) -> Result<Releve, ParseError> {
+    let parser = detect_statement_parser(lines).ok_or(ParseError::UnknownLayout {
+        provenance: Provenance::default(),
+    })?;
+    parser.parse(lines, profile)
+}
@@ -0,0 +1,5 @@
+pub mod model;
+pub mod sqlite;
+
+pub use model::{ImportLogEntry, ImportOutcome};
+pub use sqlite::{Store, StoreError, content_hash};
@@ -0,0 +1,25 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// What happened to a releve given to `Store::import_releve`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ImportOutcome {
+    /// New releve; operations already in the store (from another releve) are skipped
+    Inserted { operations: usize, skipped: usize },
+    /// The same releve, with the same content, was already imported
+    Unchanged,
+    /// A releve of the same account and date, with a different content, is in the store
+    Conflict { existing_hash: String },
+}
+
+/// A line of the import log: every call to `Store::import_releve`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportLogEntry {
+    pub imported_at: String,
+    pub source: String,
+    pub account: String,
+    pub date_du_releve: NaiveDate,
+    pub content_hash: String,
+    pub outcome: String,
+    pub operations: usize,
+}
@@ -0,0 +1,477 @@
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, params};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+
+use super::model::{ImportLogEntry, ImportOutcome};
+use crate::parser::continuity::signed_solde;
+use crate::parser::{Operation, OperationDetails, Releve, Solde, SoldeType};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS releves (
+    id INTEGER PRIMARY KEY,
+    account TEXT NOT NULL,
+    date_du_releve TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    solde_ouverture INTEGER NOT NULL,
+    solde_cloture INTEGER NOT NULL,
+    total_des_operations_debit INTEGER NOT NULL,
+    total_des_operations_credit INTEGER NOT NULL,
+    UNIQUE (account, date_du_releve)
+);
+CREATE TABLE IF NOT EXISTS operations (
+    id INTEGER PRIMARY KEY,
+    releve_id INTEGER NOT NULL REFERENCES releves (id),
+    account TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    date TEXT NOT NULL,
+    valeur TEXT NOT NULL,
+    nature_des_operations TEXT NOT NULL,
+    montant INTEGER NOT NULL,
+    details TEXT,
+    UNIQUE (account, hash)
+);
+CREATE TABLE IF NOT EXISTS releve_operations (
+    releve_id INTEGER NOT NULL REFERENCES releves (id),
+    hash TEXT NOT NULL,
+    PRIMARY KEY (releve_id, hash)
+);
+CREATE TABLE IF NOT EXISTS import_log (
+    id INTEGER PRIMARY KEY,
+    imported_at TEXT NOT NULL,
+    source TEXT NOT NULL,
+    account TEXT NOT NULL,
+    date_du_releve TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    operations INTEGER NOT NULL
+);
+";
+
+#[derive(Debug)]
+pub enum StoreError {
+    Sqlite(rusqlite::Error),
+    /// The details of an operation cannot be (de)serialized
+    Details(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Sqlite(e) => write!(f, "SQLite error: {e}"),
+            StoreError::Details(message) => write!(f, "Bad operation details: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        StoreError::Sqlite(e)
+    }
+}
+
+fn signed_montant(operation: &Operation) -> i64 {
+    match operation.montant_type {
+        SoldeType::Credit => operation.montant,
+        SoldeType::Debit => -operation.montant,
+    }
+}
+
+fn unsigned(signed: i64) -> (SoldeType, i64) {
+    if signed < 0 {
+        (SoldeType::Debit, -signed)
+    } else {
+        (SoldeType::Credit, signed)
+    }
+}
+
+fn hex_digest(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn operation_content(operation: &Operation) -> String {
+    format!(
+        "{}|{}|{}|{}",
+        operation.date,
+        operation.valeur,
+        operation.nature_des_operations,
+        signed_montant(operation)
+    )
+}
+
+/// SHA-256 of the soldes, totals and operations of a releve. The details of
+/// the operations are left out: they are derived from the descriptions.
+pub fn content_hash(releve: &Releve) -> String {
+    let mut content = format!(
+        "{}|{}|{}|{}|{}\n",
+        releve.date_du_releve,
+        signed_solde(&releve.solde_ouverture),
+        signed_solde(&releve.solde_cloture),
+        releve.total_des_operations_debit,
+        releve.total_des_operations_credit
+    );
+    for operation in &releve.operations {
+        content.push_str(&operation_content(operation));
+        content.push('\n');
+    }
+    hex_digest(&content)
+}
+
+/// Hashes of the operations of a releve. Identical operations of the same
+/// releve (two coffees on the same day) are told apart by their rank.
+fn operation_hashes(releve: &Releve) -> Vec<String> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    releve
+        .operations
+        .iter()
+        .map(|operation| {
+            let content = operation_content(operation);
+            let rank = seen.entry(content.clone()).or_insert(0);
+            *rank += 1;
+            hex_digest(&format!("{content}|{rank}"))
+        })
+        .collect()
+}
+
+/// Operations and releves of several accounts, kept in a SQLite database.
+///
+/// Releves are keyed by account and date du releve, operations by account
+/// and a hash of their content, so that importing the same statement twice,
+/// or two statements sharing operations, never counts an operation twice.
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Open the database at `path`, creating it if needed.
+    pub fn open(path: &str) -> Result<Store, StoreError> {
+        Store::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Store, StoreError> {
+        Store::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Store, StoreError> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Store { conn })
+    }
+
+    /// Insert a releve of `account`, read from `source` (a file name), and
+    /// its operations, unless it was already imported. Every call is logged.
+    pub fn import_releve(
+        &mut self,
+        account: &str,
+        releve: &Releve,
+        source: &str,
+    ) -> Result<ImportOutcome, StoreError> {
+        let hash = content_hash(releve);
+        let tx = self.conn.transaction()?;
+
+        let existing: Option<String> = tx
+            .query_row(
+                "SELECT content_hash FROM releves WHERE account = ?1 AND date_du_releve = ?2",
+                params![account, releve.date_du_releve],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let outcome = match existing {
+            Some(existing_hash) if existing_hash == hash => ImportOutcome::Unchanged,
+            Some(existing_hash) => ImportOutcome::Conflict { existing_hash },
+            None => {
+                tx.execute(
+                    "INSERT INTO releves (account, date_du_releve, content_hash, solde_ouverture,
+                         solde_cloture, total_des_operations_debit, total_des_operations_credit)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        account,
+                        releve.date_du_releve,
+                        hash,
+                        signed_solde(&releve.solde_ouverture),
+                        signed_solde(&releve.solde_cloture),
+                        releve.total_des_operations_debit,
+                        releve.total_des_operations_credit
+                    ],
+                )?;
+                let releve_id = tx.last_insert_rowid();
+
+                let mut inserted = 0;
+                for (operation, operation_hash) in
+                    releve.operations.iter().zip(operation_hashes(releve))
+                {
+                    let details = operation
+                        .details
+                        .as_ref()
+                        .map(serde_yaml::to_string)
+                        .transpose()
+                        .map_err(|e| StoreError::Details(e.to_string()))?;
+                    // Every releve containing the operation, even when it
+                    // is stored with another one
+                    tx.execute(
+                        "INSERT OR IGNORE INTO releve_operations (releve_id, hash) VALUES (?1, ?2)",
+                        params![releve_id, operation_hash],
+                    )?;
+                    inserted += tx.execute(
+                        "INSERT OR IGNORE INTO operations (releve_id, account, hash, date, valeur,
+                             nature_des_operations, montant, details)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        params![
+                            releve_id,
+                            account,
+                            operation_hash,
+                            operation.date,
+                            operation.valeur,
+                            operation.nature_des_operations,
+                            signed_montant(operation),
+                            details
+                        ],
+                    )?;
+                }
+                ImportOutcome::Inserted {
+                    operations: inserted,
+                    skipped: releve.operations.len() - inserted,
+                }
+            }
+        };
+
+        let (outcome_name, operations) = match &outcome {
+            ImportOutcome::Inserted { operations, .. } => ("inserted", *operations),
+            ImportOutcome::Unchanged => ("unchanged", 0),
+            ImportOutcome::Conflict { .. } => ("conflict", 0),
+        };
+        tx.execute(
+            "INSERT INTO import_log (imported_at, source, account, date_du_releve, content_hash,
+                 outcome, operations)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+                source,
+                account,
+                releve.date_du_releve,
+                hash,
+                outcome_name,
+                operations as i64
+            ],
+        )?;
+        tx.commit()?;
+        Ok(outcome)
+    }
+
+    /// Remove a releve and its operations, e.g. to import it again after a
+    /// parser fix. An operation another releve of the account contains too
+    /// is moved to the first of them rather than removed. Returns false if
+    /// there was no such releve.
+    pub fn remove_releve(
+        &mut self,
+        account: &str,
+        date_du_releve: NaiveDate,
+    ) -> Result<bool, StoreError> {
+        let tx = self.conn.transaction()?;
+        let releve_id: Option<i64> = tx
+            .query_row(
+                "SELECT id FROM releves WHERE account = ?1 AND date_du_releve = ?2",
+                params![account, date_du_releve],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(releve_id) = releve_id else {
+            return Ok(false);
+        };
+
+        tx.execute(
+            "UPDATE operations SET releve_id = (
+                 SELECT r.id FROM releve_operations l JOIN releves r ON r.id = l.releve_id
+                 WHERE l.hash = operations.hash AND r.account = operations.account
+                   AND r.id != ?1
+                 ORDER BY r.date_du_releve LIMIT 1)
+             WHERE releve_id = ?1 AND EXISTS (
+                 SELECT 1 FROM releve_operations l JOIN releves r ON r.id = l.releve_id
+                 WHERE l.hash = operations.hash AND r.account = operations.account
+                   AND r.id != ?1)",
+            params![releve_id],
+        )?;
+        tx.execute(
+            "DELETE FROM operations WHERE releve_id = ?1",
+            params![releve_id],
+        )?;
+        tx.execute(
+            "DELETE FROM releve_operations WHERE releve_id = ?1",
+            params![releve_id],
+        )?;
+        tx.execute("DELETE FROM releves WHERE id = ?1", params![releve_id])?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    pub fn accounts(&self) -> Result<Vec<String>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT account FROM releves ORDER BY account")?;
+        let accounts = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(accounts)
+    }
+
+    /// Operations of `account` (all accounts if None) dated between `from`
+    /// and `to` included, by date.
+    pub fn operations(
+        &self,
+        account: Option<&str>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<Operation>, StoreError> {
+        self.read_operations(
+            "SELECT date, valeur, nature_des_operations, montant, details FROM operations
+             WHERE (?1 IS NULL OR account = ?1)
+               AND (?2 IS NULL OR date >= ?2)
+               AND (?3 IS NULL OR date <= ?3)
+             ORDER BY date, id",
+            params![account, from, to],
+        )
+    }
+
+    /// The releves of `account` (all accounts if None) by date, each with all
+    /// the operations it was imported with, even those stored with another
+    /// releve: two overlapping releves both list the operations they share.
+    pub fn releves(&self, account: Option<&str>) -> Result<Vec<Releve>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, date_du_releve, solde_ouverture, solde_cloture,
+                 total_des_operations_debit, total_des_operations_credit, account
+             FROM releves WHERE (?1 IS NULL OR account = ?1)
+             ORDER BY date_du_releve, account",
+        )?;
+        let rows = stmt
+            .query_map(params![account], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, NaiveDate>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, String>(6)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut releves = Vec::new();
+        for (id, date_du_releve, ouverture, cloture, debit, credit, account) in rows {
+            let operations = self.releve_operations(id)?;
+            let check = |montant_type: fn(&SoldeType) -> bool| -> i64 {
+                operations
+                    .iter()
+                    .filter(|op| montant_type(&op.montant_type))
+                    .map(|op| op.montant)
+                    .sum()
+            };
+            let solde = |signed: i64| {
+                let (solde_type, montant) = unsigned(signed);
+                Solde {
+                    solde_type,
+                    montant,
+                }
+            };
+            let mut releve = Releve {
+                date_du_releve,
+                solde_ouverture: solde(ouverture),
+                solde_cloture: solde(cloture),
+                total_des_operations_debit: debit,
+                total_des_operations_credit: credit,
+                check_debit: check(|t| matches!(t, SoldeType::Debit)),
+                check_credit: check(|t| matches!(t, SoldeType::Credit)),
+                operations,
+                account: Some(account),
+            };
+            releve.assign_operation_ids();
+            releves.push(releve);
+        }
+        Ok(releves)
+    }
+
+    /// The operations of a releve, in the order they were imported in
+    fn releve_operations(&self, releve_id: i64) -> Result<Vec<Operation>, StoreError> {
+        let operations = self.read_operations(
+            "SELECT o.date, o.valeur, o.nature_des_operations, o.montant, o.details
+             FROM releve_operations l
+             JOIN releves r ON r.id = l.releve_id
+             JOIN operations o ON o.account = r.account AND o.hash = l.hash
+             WHERE l.releve_id = ?1 ORDER BY l.rowid",
+            params![releve_id],
+        )?;
+        if !operations.is_empty() {
+            return Ok(operations);
+        }
+        // Releves imported before `releve_operations` was recorded
+        self.read_operations(
+            "SELECT date, valeur, nature_des_operations, montant, details FROM operations
+             WHERE releve_id = ?1 ORDER BY id",
+            params![releve_id],
+        )
+    }
+
+    /// Run a query selecting date, valeur, nature_des_operations, montant and details.
+    fn read_operations(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Operation>, StoreError> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok((
+                    row.get::<_, NaiveDate>(0)?,
+                    row.get::<_, NaiveDate>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(date, valeur, nature_des_operations, montant, details)| {
+                let details = details
+                    .map(|d| serde_yaml::from_str::<OperationDetails>(&d))
+                    .transpose()
+                    .map_err(|e| StoreError::Details(e.to_string()))?;
+                let (montant_type, montant) = unsigned(montant);
+                Ok(Operation {
+                    id: None,
+                    date,
+                    nature_des_operations,
+                    valeur,
+                    montant,
+                    montant_type,
+                    details,
+                })
+            })
+            .collect()
+    }
+
+    /// Every import, oldest first.
+    pub fn import_log(&self) -> Result<Vec<ImportLogEntry>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT imported_at, source, account, date_du_releve, content_hash, outcome, operations
+             FROM import_log ORDER BY id",
+        )?;
+        let entries = stmt
+            .query_map([], |row| {
+                Ok(ImportLogEntry {
+                    imported_at: row.get(0)?,
+                    source: row.get(1)?,
+                    account: row.get(2)?,
+                    date_du_releve: row.get(3)?,
+                    content_hash: row.get(4)?,
+                    outcome: row.get(5)?,
+                    operations: row.get::<_, i64>(6)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+}
@@ -0,0 +1,316 @@
+use super::model::{Assignment, Pattern, Resolution, VentilationSpec};
+use super::overrides::{Override, operation_key};
+use super::ventilate::{MatchInfo, VentilateError};
+use crate::parser::model::{Operation, SoldeType};
+use regex::{Regex, RegexSet};
+use std::collections::HashMap;
+
+/// How the description part of a pattern is matched
+#[derive(Debug, Clone, Copy)]
+enum Description {
+    /// Index of the regex in the `RegexSet`
+    Regex(usize),
+    /// Rule without a regex: any description
+    Any,
+}
+
+/// The assignments of one side, their regexes compiled into one `RegexSet`
+#[derive(Debug)]
+struct CompiledAssignments {
+    set: RegexSet,
+    /// The regexes of the set one by one, to find where they match
+    regexes: Vec<Regex>,
+    /// For each assignment, for each pattern
+    descriptions: Vec<Vec<Description>>,
+}
+
+impl CompiledAssignments {
+    fn new(assignments: &[Assignment]) -> Result<Self, regex::Error> {
+        let mut regexes: Vec<&str> = Vec::new();
+        let descriptions = assignments
+            .iter()
+            .map(|assignment| {
+                assignment
+                    .patterns
+                    .iter()
+                    .map(|pattern| match pattern.regex() {
+                        None => Description::Any,
+                        Some(regex) => {
+                            regexes.push(regex);
+                            Description::Regex(regexes.len() - 1)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let set = RegexSet::new(&regexes)?;
+        let regexes = regexes
+            .into_iter()
+            .map(Regex::new)
+            .collect::<Result<_, _>>()?;
+        Ok(CompiledAssignments {
+            set,
+            regexes,
+            descriptions,
+        })
+    }
+
+    /// The part of `description` pattern `j` of assignment `i` matches
+    fn find_span(&self, i: usize, j: usize, description: &str) -> Option<(usize, usize)> {
+        match self.descriptions.get(i)?.get(j)? {
+            Description::Regex(index) => self.regexes[*index]
+                .find(description)
+                .map(|m| (m.start(), m.end())),
+            Description::Any => Some((0, description.len())),
+        }
+    }
+
+    /// The matching patterns, as (assignment, pattern) indices in the order of the spec
+    fn matching_patterns(
+        &self,
+        assignments: &[Assignment],
+        operation: &Operation,
+        account: Option<&str>,
+    ) -> Vec<(usize, usize)> {
+        let hits = self.set.matches(&operation.nature_des_operations);
+        let mut matching = Vec::new();
+        for (i, (assignment, descriptions)) in
+            assignments.iter().zip(&self.descriptions).enumerate()
+        {
+            for (j, (pattern, description)) in
+                assignment.patterns.iter().zip(descriptions).enumerate()
+            {
+                let description_matches = match description {
+                    Description::Regex(index) => hits.matched(*index),
+                    Description::Any => true,
+                };
+                let accepted = match pattern {
+                    Pattern::Regex(_) => true,
+                    Pattern::Rule(rule) => rule.accepts(operation, account),
+                };
+                if description_matches && accepted {
+                    matching.push((i, j));
+                }
+            }
+        }
+        matching
+    }
+}
+
+/// A ventilation spec compiled once to be matched against many operations:
+/// the regexes of each side in a `RegexSet`, the overrides by identifier.
+#[derive(Debug)]
+pub struct CompiledSpec {
+    spec: VentilationSpec,
+    debit: CompiledAssignments,
+    credit: CompiledAssignments,
+    /// Index in `spec.overrides` by `Override::id`
+    overrides: HashMap<String, usize>,
+}
+
+impl CompiledSpec {
+    /// Compile the patterns of `spec`, failing with
+    /// `VentilateError::InvalidPatterns` on the regexes that do not compile,
+    /// or `VentilateError::RegexSet` when they only do one by one, and with
+    /// `VentilateError::InvalidSplits` on the splits that cannot be allocated.
+    pub fn new(spec: VentilationSpec) -> Result<Self, VentilateError> {
+        let compile = |montant_type: SoldeType| {
+            CompiledAssignments::new(spec.side(&montant_type)).map_err(|e| (montant_type, e))
+        };
+        let (debit, credit) = match (compile(SoldeType::Debit), compile(SoldeType::Credit)) {
+            (Ok(debit), Ok(credit)) => (debit, credit),
+            (Err((montant_type, error)), _) | (_, Err((montant_type, error))) => {
+                // Compile the regexes one by one only to tell which ones fail
+                spec.validate()?;
+                return Err(VentilateError::RegexSet {
+                    montant_type,
+                    error: error.to_string(),
+                });
+            }
+        };
+        spec.validate_splits()?;
+        let mut overrides = HashMap::new();
+        for (index, o) in spec.overrides.iter().enumerate() {
+            // The first override of an operation wins
+            overrides.entry(o.id.clone()).or_insert(index);
+        }
+        Ok(CompiledSpec {
+            spec,
+            debit,
+            credit,
+            overrides,
+        })
+    }
+
+    pub fn spec(&self) -> &VentilationSpec {
+        &self.spec
+    }
+
+    pub fn into_spec(self) -> VentilationSpec {
+        self.spec
+    }
+
+    /// The assignments debits (`spec.assignments`) or credits (`spec.income`) are matched against
+    pub fn assignments(&self, montant_type: &SoldeType) -> &[Assignment] {
+        self.spec.side(montant_type)
+    }
+
+    /// The override of `operation`, by its `id` or its `operation_key`
+    pub fn find_override(&self, operation: &Operation) -> Option<&Override> {
+        if self.overrides.is_empty() {
+            return None;
+        }
+        let index = operation
+            .id
+            .as_ref()
+            .and_then(|id| self.overrides.get(id))
+            .or_else(|| self.overrides.get(&operation_key(operation)))?;
+        Some(&self.spec.overrides[*index])
+    }
+
+    /// The first matching pattern of each assignment of the side of the
+    /// operation, in the order of the spec. `account` is the account of the
+    /// releve of the operation.
+    pub fn find_matches(&self, operation: &Operation, account: Option<&str>) -> Vec<MatchInfo> {
+        let compiled = match operation.montant_type {
+            SoldeType::Debit => &self.debit,
+            SoldeType::Credit => &self.credit,
+        };
+        let assignments = self.assignments(&operation.montant_type);
+        let mut matches: Vec<MatchInfo> = Vec::new();
+        let mut last = None;
+        for (i, j) in compiled.matching_patterns(assignments, operation, account) {
+            // Only the first pattern of each assignment
+            if last == Some(i) {
+                continue;
+            }
+            last = Some(i);
+            matches.push(MatchInfo {
+                assignment: assignments[i].name.clone(),
+                pattern: assignments[i].patterns[j].to_string(),
+            });
+        }
+        matches
+    }
+
+    /// Every matching pattern of the side of the operation, not only the first
+    /// one of each assignment, as (assignment, pattern) indices in
+    /// `assignments(&operation.montant_type)`.
+    pub fn matching_patterns(
+        &self,
+        operation: &Operation,
+        account: Option<&str>,
+    ) -> Vec<(usize, usize)> {
+        let compiled = match operation.montant_type {
+            SoldeType::Debit => &self.debit,
+            SoldeType::Credit => &self.credit,
+        };
+        compiled.matching_patterns(
+            self.assignments(&operation.montant_type),
+            operation,
+            account,
+        )
+    }
+
+    /// The byte range of `description` matched by the regex of pattern `j` of
+    /// assignment `i` of the side `montant_type`, `None` if it does not match;
+    /// the whole description for a rule without a regex. The other conditions
+    /// of a rule are not checked.
+    pub fn find_span(
+        &self,
+        montant_type: &SoldeType,
+        i: usize,
+        j: usize,
+        description: &str,
+    ) -> Option<(usize, usize)> {
+        let compiled = match montant_type {
+            SoldeType::Debit => &self.debit,
+            SoldeType::Credit => &self.credit,
+        };
+        compiled.find_span(i, j, description)
+    }
+
+    /// The match an operation is assigned to, by its override or according to
+    /// the `resolution` of the spec, with all the matches when there are
+    /// several of them.
+    pub fn assign(
+        &self,
+        operation: &Operation,
+        account: Option<&str>,
+    ) -> Result<(Option<MatchInfo>, Vec<MatchInfo>), VentilateError> {
+        // Overrides come before the patterns
+        if let Some(o) = self.find_override(operation) {
+            let assigned = MatchInfo {
+                assignment: o.assignment.clone(),
+                pattern: format!("override {}", o.id),
+            };
+            return Ok((Some(assigned), Vec::new()));
+        }
+
+        let mut matches = self.find_matches(operation, account);
+        if matches.len() <= 1 {
+            return Ok((matches.pop(), Vec::new()));
+        }
+
+        let assignments = self.assignments(&operation.montant_type);
+        let priority = |m: &MatchInfo| {
+            assignments
+                .iter()
+                .find(|a| a.name == m.assignment)
+                .map_or(0, |a| a.priority)
+        };
+        // Matches are in the order of the spec: the first one wins ties
+        let best = |key: &dyn Fn(&MatchInfo) -> i64| {
+            (0..matches.len())
+                .max_by_key(|&i| (key(&matches[i]), std::cmp::Reverse(i)))
+                .unwrap_or(0)
+        };
+        let mut chosen_pattern = None;
+        let chosen = match self.spec.resolution {
+            Resolution::Strict => {
+                return Err(VentilateError::MultipleMatch {
+                    operation: operation.nature_des_operations.clone(),
+                    matches,
+                });
+            }
+            Resolution::Priority => best(&|m| i64::from(priority(m))),
+            Resolution::MostSpecific => {
+                // Every matching pattern of an assignment counts, not only its
+                // first one: the most specific of each, the first one on ties
+                let mut longest: HashMap<&str, (i64, String)> = HashMap::new();
+                for (i, j) in self.matching_patterns(operation, account) {
+                    let pattern = &assignments[i].patterns[j];
+                    let length = pattern.specificity() as i64;
+                    let entry = longest
+                        .entry(assignments[i].name.as_str())
+                        .or_insert((length, pattern.to_string()));
+                    if length > entry.0 {
+                        *entry = (length, pattern.to_string());
+                    }
+                }
+                let chosen = best(&|m| longest.get(m.assignment.as_str()).map_or(0, |l| l.0));
+                chosen_pattern = longest.remove(matches[chosen].assignment.as_str());
+                chosen
+            }
+            Resolution::FirstInFile => 0,
+        };
+        let mut assigned = matches[chosen].clone();
+        if let Some((_, pattern)) = chosen_pattern {
+            assigned.pattern = pattern;
+        }
+        Ok((Some(assigned), matches))
+    }
+
+    /// The categories an operation assigned to `match_info` goes to, with
+    /// their part of its amount.
+    pub fn allocation(&self, match_info: &MatchInfo, operation: &Operation) -> Vec<(String, i64)> {
+        match self
+            .assignments(&operation.montant_type)
+            .iter()
+            .find(|a| a.name == match_info.assignment)
+        {
+            Some(assignment) => assignment.allocate(operation.montant),
+            None => vec![(match_info.assignment.clone(), operation.montant)],
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use super::compiled::CompiledSpec;
+use super::model::section;
+use super::ventilate::MatchInfo;
+use crate::parser::model::SoldeType;
+use serde::Serialize;
+
+/// An example or counter example of an assignment the compiled spec gets wrong
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExampleFailure {
+    /// `Debit` for `assignments`, `Credit` for `income`
+    pub montant_type: SoldeType,
+    pub assignment: String,
+    pub description: String,
+    /// `false` for a counter example
+    pub expected_match: bool,
+    /// The assignment the example ends up in, if any
+    pub assigned: Option<String>,
+    /// Every assignment matching the example, with its first matching pattern
+    pub matches: Vec<MatchInfo>,
+}
+
+impl std::fmt::Display for ExampleFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let section = section(&self.montant_type);
+        let kind = if self.expected_match {
+            "example"
+        } else {
+            "counter example"
+        };
+        writeln!(
+            f,
+            "{section} '{}', {kind} '{}':",
+            self.assignment, self.description
+        )?;
+        if self.expected_match {
+            writeln!(f, "  - assigned to {}", self.assignment)?;
+        } else {
+            writeln!(f, "  - not assigned to {}", self.assignment)?;
+        }
+        match &self.assigned {
+            Some(assigned) => writeln!(f, "  + assigned to {assigned}")?,
+            None if self.matches.len() > 1 => writeln!(f, "  + matched by several assignments")?,
+            None => writeln!(f, "  + not assigned")?,
+        }
+        for m in &self.matches {
+            writeln!(
+                f,
+                "  + matched by {} (pattern: {})",
+                m.assignment, m.pattern
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the examples and counter examples of every assignment against the
+/// compiled spec, with the `resolution` of the spec: an example must be
+/// assigned to its assignment, a counter example must end up anywhere else.
+pub fn check_examples(compiled: &CompiledSpec) -> Vec<ExampleFailure> {
+    let mut failures = Vec::new();
+    for montant_type in [SoldeType::Debit, SoldeType::Credit] {
+        for assignment in compiled.assignments(&montant_type) {
+            let cases = assignment
+                .examples
+                .iter()
+                .map(|example| (example, true))
+                .chain(assignment.counter_examples.iter().map(|e| (e, false)));
+            for (example, expected_match) in cases {
+                let operation = example.operation(montant_type.clone());
+                let account = example.account();
+                let matches = compiled.find_matches(&operation, account);
+                let assigned = match compiled.assign(&operation, account) {
+                    Ok((assigned, _)) => assigned.map(|m| m.assignment),
+                    // Several matches with the strict resolution
+                    Err(_) => None,
+                };
+                let in_assignment = match &assigned {
+                    Some(assigned) => *assigned == assignment.name,
+                    // Ventilating would fail if the assignment is one of the matches
+                    None => matches.iter().any(|m| m.assignment == assignment.name),
+                };
+                let ok = if expected_match {
+                    assigned.as_deref() == Some(assignment.name.as_str())
+                } else {
+                    !in_assignment
+                };
+                if !ok {
+                    failures.push(ExampleFailure {
+                        montant_type: montant_type.clone(),
+                        assignment: assignment.name.clone(),
+                        description: example.description().to_string(),
+                        expected_match,
+                        assigned,
+                        matches,
+                    });
+                }
+            }
+        }
+    }
+    failures
+}
@@ -0,0 +1,310 @@
+use super::compiled::CompiledSpec;
+use super::model::{Pattern, Resolution, section};
+use super::overrides::operation_key;
+use super::transfers::Transfer;
+use super::ventilate::MatchInfo;
+use crate::parser::model::{Operation, Releve, SoldeType, euros};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// How to find the operations to explain
+#[derive(Debug, Clone, PartialEq)]
+pub enum OperationQuery {
+    /// `id` of the operation, or its `operation_key`
+    Id(String),
+    /// Operation date and amount in centimes: a negative amount only finds
+    /// debits, a positive one debits and credits
+    DateAmount { date: NaiveDate, montant: i64 },
+    /// Part of the description, whatever the case
+    Description(String),
+}
+
+impl OperationQuery {
+    pub fn matches(&self, operation: &Operation) -> bool {
+        match self {
+            OperationQuery::Id(id) => {
+                operation.id.as_ref() == Some(id) || operation_key(operation) == *id
+            }
+            OperationQuery::DateAmount { date, montant } => {
+                operation.date == *date
+                    && operation.montant == montant.abs()
+                    && (*montant >= 0 || operation.montant_type == SoldeType::Debit)
+            }
+            OperationQuery::Description(text) => operation
+                .nature_des_operations
+                .to_lowercase()
+                .contains(&text.to_lowercase()),
+        }
+    }
+}
+
+/// An operation found in the releves
+#[derive(Debug, Clone)]
+pub struct FoundOperation<'a> {
+    pub operation: &'a Operation,
+    /// `account` of its releve
+    pub account: Option<&'a str>,
+    /// Index of the releve and of the operation in it
+    pub position: (usize, usize),
+}
+
+/// The operations of the releves found by `query`
+pub fn find_operations<'a>(data: &'a [Releve], query: &OperationQuery) -> Vec<FoundOperation<'a>> {
+    data.iter()
+        .enumerate()
+        .flat_map(|(r, releve)| {
+            releve
+                .operations
+                .iter()
+                .enumerate()
+                .map(move |(o, operation)| FoundOperation {
+                    operation,
+                    account: releve.account.as_deref(),
+                    position: (r, o),
+                })
+        })
+        .filter(|found| query.matches(found.operation))
+        .collect()
+}
+
+/// One pattern of an assignment tried on the operation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PatternTrial {
+    /// Position of the pattern in the assignment, starting at 1
+    pub position: usize,
+    pub pattern: String,
+    /// Byte range of the description matched by the regex, `None` if it does
+    /// not match; the whole description for a rule without a regex
+    pub span: Option<(usize, usize)>,
+    /// Whether the other conditions of a rule hold, always for a plain regex
+    pub conditions: bool,
+}
+
+impl PatternTrial {
+    pub fn matched(&self) -> bool {
+        self.span.is_some() && self.conditions
+    }
+}
+
+/// The patterns of one assignment tried on the operation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssignmentTrial {
+    pub name: String,
+    pub priority: i32,
+    pub patterns: Vec<PatternTrial>,
+}
+
+/// What put the operation where it ended up
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Decision {
+    /// A leg of an internal transfer, left out of the categories before the
+    /// override and the patterns
+    InternalTransfer {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// An override, applied before the patterns
+    Override { id: String, assignment: String },
+    /// The only matching assignment
+    Single(MatchInfo),
+    /// Several assignments match, the `resolution` of the spec chose one
+    Resolved {
+        resolution: Resolution,
+        chosen: MatchInfo,
+    },
+    /// Several assignments match with the strict resolution: `ventilate` fails
+    Ambiguous,
+    /// No pattern matches: the operation goes to `not_assigned`
+    NotAssigned,
+}
+
+/// Why an operation was categorised the way it was
+#[derive(Debug, Clone, Serialize)]
+pub struct Explanation {
+    pub operation: Operation,
+    pub account: Option<String>,
+    /// Every assignment of the side of the operation, in the order of the spec
+    pub assignments: Vec<AssignmentTrial>,
+    /// The matching assignments, with their first matching pattern
+    pub matches: Vec<MatchInfo>,
+    pub decision: Decision,
+    /// Categories and their part of the amount, with the shares of a `split`
+    pub allocation: Vec<(String, i64)>,
+}
+
+/// Try every pattern of the side of `operation` and tell how it is assigned,
+/// `account` being the account of its releve and `transfer` the internal
+/// transfer it is a leg of, if any.
+pub fn explain(
+    compiled: &CompiledSpec,
+    operation: &Operation,
+    account: Option<&str>,
+    transfer: Option<&Transfer>,
+) -> Explanation {
+    let assignments = compiled
+        .assignments(&operation.montant_type)
+        .iter()
+        .enumerate()
+        .map(|(i, assignment)| AssignmentTrial {
+            name: assignment.name.clone(),
+            priority: assignment.priority,
+            patterns: assignment
+                .patterns
+                .iter()
+                .enumerate()
+                .map(|(j, pattern)| PatternTrial {
+                    position: j + 1,
+                    pattern: pattern.to_string(),
+                    span: compiled.find_span(
+                        &operation.montant_type,
+                        i,
+                        j,
+                        &operation.nature_des_operations,
+                    ),
+                    conditions: match pattern {
+                        Pattern::Regex(_) => true,
+                        Pattern::Rule(rule) => rule.accepts(operation, account),
+                    },
+                })
+                .collect(),
+        })
+        .collect();
+    let matches = compiled.find_matches(operation, account);
+
+    let assign = match transfer {
+        Some(transfer) => Err(Decision::InternalTransfer {
+            from: transfer.from().map(String::from),
+            to: transfer.to().map(String::from),
+        }),
+        // Several matches with the strict resolution
+        None => compiled
+            .assign(operation, account)
+            .map_err(|_| Decision::Ambiguous),
+    };
+    let (decision, assigned) = match assign {
+        Ok((Some(assigned), _)) => {
+            let decision = match compiled.find_override(operation) {
+                Some(o) => Decision::Override {
+                    id: o.id.clone(),
+                    assignment: o.assignment.clone(),
+                },
+                None if matches.len() == 1 => Decision::Single(assigned.clone()),
+                None => Decision::Resolved {
+                    resolution: compiled.spec().resolution,
+                    chosen: assigned.clone(),
+                },
+            };
+            (decision, Some(assigned))
+        }
+        Ok((None, _)) => (Decision::NotAssigned, None),
+        Err(decision) => (decision, None),
+    };
+    let allocation = match &assigned {
+        Some(assigned) => compiled.allocation(assigned, operation),
+        None => Vec::new(),
+    };
+
+    Explanation {
+        operation: operation.clone(),
+        account: account.map(String::from),
+        assignments,
+        matches,
+        decision,
+        allocation,
+    }
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = &self.operation;
+        let section = section(&op.montant_type);
+        let sign = match op.montant_type {
+            SoldeType::Debit => "-",
+            SoldeType::Credit => "",
+        };
+        write!(
+            f,
+            "{} {} {sign}{}",
+            op.date,
+            op.nature_des_operations,
+            euros(op.montant)
+        )?;
+        if let Some(id) = &op.id {
+            write!(f, " (id {id})")?;
+        }
+        if let Some(account) = &self.account {
+            write!(f, " [{account}]")?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "{section}:")?;
+        for assignment in &self.assignments {
+            writeln!(
+                f,
+                "  {} (priority {})",
+                assignment.name, assignment.priority
+            )?;
+            for trial in &assignment.patterns {
+                let outcome = match (trial.span, trial.conditions) {
+                    (None, _) => "no match".to_string(),
+                    (Some(_), false) => "description matches, conditions do not hold".to_string(),
+                    (Some((start, end)), true) => {
+                        let d = &op.nature_des_operations;
+                        format!("matches {}[{}]{}", &d[..start], &d[start..end], &d[end..])
+                    }
+                };
+                writeln!(
+                    f,
+                    "    pattern {} '{}': {outcome}",
+                    trial.position, trial.pattern
+                )?;
+            }
+        }
+
+        match &self.decision {
+            Decision::InternalTransfer { from, to } => writeln!(
+                f,
+                "Internal transfer from {} to {}: left out of the categories",
+                from.as_deref().unwrap_or("?"),
+                to.as_deref().unwrap_or("?")
+            )?,
+            Decision::Override { id, assignment } => writeln!(
+                f,
+                "Override {id} assigns it to {assignment}, before the patterns"
+            )?,
+            Decision::Single(m) => {
+                writeln!(f, "Only {} matches (pattern: {})", m.assignment, m.pattern)?
+            }
+            Decision::Resolved { resolution, chosen } => {
+                let names: Vec<&str> = self.matches.iter().map(|m| m.assignment.as_str()).collect();
+                writeln!(
+                    f,
+                    "{} match, resolution {resolution:?} chose {} (pattern: {})",
+                    names.join(", "),
+                    chosen.assignment,
+                    chosen.pattern
+                )?
+            }
+            Decision::Ambiguous => {
+                let names: Vec<&str> = self.matches.iter().map(|m| m.assignment.as_str()).collect();
+                writeln!(
+                    f,
+                    "{} match with the strict resolution: ventilation fails",
+                    names.join(", ")
+                )?
+            }
+            Decision::NotAssigned => {
+                writeln!(f, "No pattern of the {section} matches: not assigned")?
+            }
+        }
+        if self.allocation.len() > 1 {
+            let parts: Vec<String> = self
+                .allocation
+                .iter()
+                .map(|(name, part)| format!("{name} {}", euros(*part)))
+                .collect();
+            writeln!(f, "Split: {}", parts.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use super::compiled::CompiledSpec;
+use super::model::PatternRef;
+use super::transfers::{find_transfers, transfer_positions};
+use crate::parser::model::{Releve, SoldeType};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// A pattern matching only operations that another pattern of the same
+/// assignment matches too
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubsumedPattern {
+    pub pattern: PatternRef,
+    pub by: PatternRef,
+    pub operations: usize,
+}
+
+/// Two patterns of different assignments matching the same operations
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Overlap {
+    pub first: PatternRef,
+    pub second: PatternRef,
+    pub operations: usize,
+    pub montant: i64,
+}
+
+/// Operations matched by the patterns of one assignment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssignmentCoverage {
+    pub name: String,
+    pub operations: usize,
+    pub montant: i64,
+}
+
+/// How much of the debits or the credits the spec categorises
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Coverage {
+    /// In the order of the spec
+    pub assignments: Vec<AssignmentCoverage>,
+    pub operations: usize,
+    pub montant: i64,
+    /// Operations matched by a pattern or an override
+    pub categorised_operations: usize,
+    pub categorised_montant: i64,
+}
+
+impl Coverage {
+    /// Percentage of the amount categorised, 100 when there is no operation
+    pub fn percent(&self) -> f64 {
+        if self.montant == 0 {
+            100.0
+        } else {
+            self.categorised_montant as f64 * 100.0 / self.montant as f64
+        }
+    }
+}
+
+/// What running a spec against releves tells about its patterns.
+///
+/// The patterns are compared by the operations they match in the releves, not
+/// by their regexes: a pattern subsumed in these releves may catch other
+/// operations next year.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct LintReport {
+    /// Patterns matching no operation
+    pub dead_patterns: Vec<PatternRef>,
+    pub subsumed_patterns: Vec<SubsumedPattern>,
+    pub overlaps: Vec<Overlap>,
+    pub debit: Coverage,
+    pub credit: Coverage,
+}
+
+/// (assignment, pattern) indices in one side of the spec
+type PatternIndex = (usize, usize);
+
+/// Run every pattern of the spec against the operations of one side.
+fn lint_side(
+    compiled: &CompiledSpec,
+    data: &[Releve],
+    montant_type: SoldeType,
+    report: &mut LintReport,
+) {
+    let assignments = compiled.assignments(&montant_type);
+    let pattern_ref = |i: usize, j: usize| PatternRef {
+        montant_type: montant_type.clone(),
+        assignment: assignments[i].name.clone(),
+        position: j + 1,
+        pattern: assignments[i].patterns[j].to_string(),
+    };
+
+    // Operations matched by each pattern, by index in the side
+    let mut matched: Vec<Vec<HashSet<usize>>> = assignments
+        .iter()
+        .map(|a| vec![HashSet::new(); a.patterns.len()])
+        .collect();
+    let mut coverage = Coverage {
+        assignments: assignments
+            .iter()
+            .map(|a| AssignmentCoverage {
+                name: a.name.clone(),
+                operations: 0,
+                montant: 0,
+            })
+            .collect(),
+        ..Coverage::default()
+    };
+    // Operations and amount by pair of patterns
+    let mut overlaps: BTreeMap<(PatternIndex, PatternIndex), (usize, i64)> = BTreeMap::new();
+
+    // Internal transfers are not categorised
+    let transfers = transfer_positions(&find_transfers(data, &compiled.spec().transfers));
+    let side = &montant_type;
+    let operations = data.iter().enumerate().flat_map(|(r, releve)| {
+        let transfers = &transfers;
+        releve
+            .operations
+            .iter()
+            .enumerate()
+            .filter(move |&(o, op)| op.montant_type == *side && !transfers.contains(&(r, o)))
+            .map(move |(_, op)| (op, releve.account.as_deref()))
+    });
+    for (index, (operation, account)) in operations.enumerate() {
+        let matching = compiled.matching_patterns(operation, account);
+        coverage.operations += 1;
+        coverage.montant += operation.montant;
+        if !matching.is_empty() || compiled.find_override(operation).is_some() {
+            coverage.categorised_operations += 1;
+            coverage.categorised_montant += operation.montant;
+        }
+
+        let mut last = None;
+        for &(i, j) in &matching {
+            matched[i][j].insert(index);
+            if last != Some(i) {
+                coverage.assignments[i].operations += 1;
+                coverage.assignments[i].montant += operation.montant;
+                last = Some(i);
+            }
+        }
+        for (k, &first) in matching.iter().enumerate() {
+            for &second in &matching[k + 1..] {
+                if first.0 != second.0 {
+                    let overlap = overlaps.entry((first, second)).or_insert((0, 0));
+                    overlap.0 += 1;
+                    overlap.1 += operation.montant;
+                }
+            }
+        }
+    }
+
+    for (i, patterns) in matched.iter().enumerate() {
+        for (j, operations) in patterns.iter().enumerate() {
+            if operations.is_empty() {
+                report.dead_patterns.push(pattern_ref(i, j));
+                continue;
+            }
+            // Of two patterns matching the same operations, the second one is subsumed
+            let by = patterns.iter().enumerate().find(|&(k, other)| {
+                k != j && operations.is_subset(other) && (other.len() > operations.len() || k < j)
+            });
+            if let Some((k, _)) = by {
+                report.subsumed_patterns.push(SubsumedPattern {
+                    pattern: pattern_ref(i, j),
+                    by: pattern_ref(i, k),
+                    operations: operations.len(),
+                });
+            }
+        }
+    }
+
+    for (((i, j), (k, l)), (operations, montant)) in overlaps {
+        report.overlaps.push(Overlap {
+            first: pattern_ref(i, j),
+            second: pattern_ref(k, l),
+            operations,
+            montant,
+        });
+    }
+
+    match montant_type {
+        SoldeType::Debit => report.debit = coverage,
+        SoldeType::Credit => report.credit = coverage,
+    }
+}
+
+/// Lint the patterns of a spec against releves: dead, subsumed and
+/// overlapping patterns, and how much of each side is categorised. Internal
+/// transfers are left out.
+pub fn lint(compiled: &CompiledSpec, data: &[Releve]) -> LintReport {
+    let mut report = LintReport::default();
+    lint_side(compiled, data, SoldeType::Debit, &mut report);
+    lint_side(compiled, data, SoldeType::Credit, &mut report);
+    report
+}
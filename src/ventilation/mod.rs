@@ -1,2 +1,9 @@
+pub mod compiled;
+pub mod examples;
+pub mod explain;
+pub mod lint;
 pub mod model;
+pub mod overrides;
+pub mod period;
+pub mod transfers;
 pub mod ventilate;
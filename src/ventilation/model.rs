@@ -1,26 +1,630 @@
-use crate::parser::model::Operation;
+use super::overrides::Override;
+use super::transfers::{Transfer, Transfers};
+use super::ventilate::{MatchInfo, VentilateError};
+use crate::parser::model::{Operation, SoldeType};
+use chrono::{Datelike, NaiveDate, Weekday};
+use indexmap::{IndexMap, IndexSet};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Separates the levels of a hierarchical category name, as in `Transport/Train`
+pub const CATEGORY_SEPARATOR: char = '/';
+
+/// The levels of a category name: `Transport/Train` -> `Transport`, `Train`
+pub fn category_levels(name: &str) -> impl Iterator<Item = &str> {
+    name.split(CATEGORY_SEPARATOR).map(str::trim)
+}
+
+/// The category itself and all its parents, the top level first:
+/// `Transport/Train/TGV` -> `Transport`, `Transport/Train`, `Transport/Train/TGV`
+pub fn category_ancestors(name: &str) -> Vec<String> {
+    let mut ancestors: Vec<String> = Vec::new();
+    for level in category_levels(name) {
+        let path = match ancestors.last() {
+            Some(parent) => format!("{parent}{CATEGORY_SEPARATOR}{level}"),
+            None => level.to_string(),
+        };
+        ancestors.push(path);
+    }
+    ancestors
+}
+
+/// The first `depth` levels of a category name: `Transport/Train` at depth 1 is `Transport`
+pub fn truncate_category(name: &str, depth: usize) -> String {
+    category_levels(name)
+        .take(depth.max(1))
+        .collect::<Vec<_>>()
+        .join(&CATEGORY_SEPARATOR.to_string())
+}
+
+/// Conditions on an operation, which must all hold for the rule to match.
+/// Amounts are in centimes and bounds are included.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Regex on `nature_des_operations`, any description if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_amount: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<i64>,
+    /// First date of the operations matched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<NaiveDate>,
+    /// Last date of the operations matched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<NaiveDate>,
+    /// Days of the week of the operation date (`Mon`, `Tue`...)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weekdays: Vec<Weekday>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub montant_type: Option<SoldeType>,
+    /// Minimum number of days from `date` to `valeur`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_valeur_delay: Option<i64>,
+    /// Maximum number of days from `date` to `valeur`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_valeur_delay: Option<i64>,
+    /// `account` of the releve
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+}
+
+impl Rule {
+    /// Whether the operation, from a releve of `account`, satisfies the
+    /// conditions other than the pattern.
+    pub fn accepts(&self, operation: &Operation, account: Option<&str>) -> bool {
+        let delay = (operation.valeur - operation.date).num_days();
+        self.min_amount.is_none_or(|min| operation.montant >= min)
+            && self.max_amount.is_none_or(|max| operation.montant <= max)
+            && self.from.is_none_or(|from| operation.date >= from)
+            && self.to.is_none_or(|to| operation.date <= to)
+            && (self.weekdays.is_empty() || self.weekdays.contains(&operation.date.weekday()))
+            && self
+                .montant_type
+                .as_ref()
+                .is_none_or(|t| *t == operation.montant_type)
+            && self.min_valeur_delay.is_none_or(|min| delay >= min)
+            && self.max_valeur_delay.is_none_or(|max| delay <= max)
+            && self.account.as_deref().is_none_or(|a| account == Some(a))
+    }
+}
+
+/// A regex on `nature_des_operations`, or a rule with more conditions
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Pattern {
+    Regex(String),
+    Rule(Rule),
+}
+
+impl Pattern {
+    /// The regex on `nature_des_operations`, if any
+    pub fn regex(&self) -> Option<&str> {
+        match self {
+            Pattern::Regex(regex) => Some(regex),
+            Pattern::Rule(rule) => rule.pattern.as_deref(),
+        }
+    }
+
+    /// How specific the description match is, for `Resolution::MostSpecific`:
+    /// the number of literal characters of the regex, 0 without a regex. Regex
+    /// syntax, classes (`[A-Z]`, `\d`) and repetitions count for nothing, and
+    /// neither do the other conditions of a rule.
+    pub fn specificity(&self) -> usize {
+        let Some(regex) = self.regex() else {
+            return 0;
+        };
+        let mut count = 0;
+        let mut chars = regex.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                // `\.` is a literal, `\d` or `\b` a class or an assertion
+                '\\' => {
+                    if chars.next().is_some_and(|e| !e.is_ascii_alphanumeric()) {
+                        count += 1;
+                    }
+                }
+                '[' => {
+                    // Skip the class, a `]` right after `[` or `[^` being a literal
+                    let mut first = true;
+                    while let Some(c) = chars.next() {
+                        match c {
+                            ']' if !first => break,
+                            '\\' => {
+                                chars.next();
+                            }
+                            _ => {}
+                        }
+                        first = c == '^' && first;
+                    }
+                }
+                '{' => while chars.next().is_some_and(|c| c != '}') {},
+                // Flags and group names: `(?i)`, `(?:`, `(?P<name>`
+                '(' if chars.peek() == Some(&'?') => {
+                    while chars
+                        .next()
+                        .is_some_and(|c| c != ')' && c != ':' && c != '>')
+                    {}
+                }
+                '.' | '^' | '$' | '*' | '+' | '?' | '(' | ')' | '|' => {}
+                _ => count += 1,
+            }
+        }
+        count
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(regex: &str) -> Self {
+        Pattern::Regex(regex.to_string())
+    }
+}
+
+impl From<String> for Pattern {
+    fn from(regex: String) -> Self {
+        Pattern::Regex(regex)
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rule = match self {
+            Pattern::Regex(regex) => return write!(f, "{regex}"),
+            Pattern::Rule(rule) => rule,
+        };
+        let mut conditions = Vec::new();
+        if let Some(min) = rule.min_amount {
+            conditions.push(format!("montant >= {min}"));
+        }
+        if let Some(max) = rule.max_amount {
+            conditions.push(format!("montant <= {max}"));
+        }
+        if let Some(from) = rule.from {
+            conditions.push(format!("date >= {from}"));
+        }
+        if let Some(to) = rule.to {
+            conditions.push(format!("date <= {to}"));
+        }
+        if !rule.weekdays.is_empty() {
+            let days: Vec<String> = rule.weekdays.iter().map(|d| d.to_string()).collect();
+            conditions.push(format!("weekday in {}", days.join(",")));
+        }
+        if let Some(montant_type) = &rule.montant_type {
+            conditions.push(format!("{montant_type:?}"));
+        }
+        if let Some(min) = rule.min_valeur_delay {
+            conditions.push(format!("valeur - date >= {min}"));
+        }
+        if let Some(max) = rule.max_valeur_delay {
+            conditions.push(format!("valeur - date <= {max}"));
+        }
+        if let Some(account) = &rule.account {
+            conditions.push(format!("account {account}"));
+        }
+        write!(f, "{}", rule.pattern.as_deref().unwrap_or("*"))?;
+        if !conditions.is_empty() {
+            write!(f, " [{}]", conditions.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// An example operation of an assignment, checked against the compiled spec
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Example {
+    /// Just a description
+    Description(String),
+    /// A description with the amount, date or account the rules look at
+    Operation(ExampleOperation),
+}
+
+/// An example operation with more than a description
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExampleOperation {
+    pub description: String,
+    /// In centimes, 0 if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub montant: Option<i64>,
+    /// Operation and value date, 1970-01-01 if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<NaiveDate>,
+    /// `account` of the releve
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+}
+
+impl Example {
+    pub fn description(&self) -> &str {
+        match self {
+            Example::Description(description) => description,
+            Example::Operation(example) => &example.description,
+        }
+    }
+
+    pub fn account(&self) -> Option<&str> {
+        match self {
+            Example::Description(_) => None,
+            Example::Operation(example) => example.account.as_deref(),
+        }
+    }
+
+    /// The operation of the example, on the side of `montant_type`
+    pub fn operation(&self, montant_type: SoldeType) -> Operation {
+        let (montant, date) = match self {
+            Example::Description(_) => (None, None),
+            Example::Operation(example) => (example.montant, example.date),
+        };
+        let date = date.unwrap_or_default();
+        Operation {
+            id: None,
+            date,
+            nature_des_operations: self.description().to_string(),
+            valeur: date,
+            montant: montant.unwrap_or(0),
+            montant_type,
+            details: None,
+        }
+    }
+}
+
+impl From<&str> for Example {
+    fn from(description: &str) -> Self {
+        Example::Description(description.to_string())
+    }
+}
+
+/// Part of the operations of an assignment allocated to another category
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Share {
+    pub name: String,
+    /// Percentage of the amount left after the fixed amounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percent: Option<f64>,
+    /// Fixed amount in centimes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Assignment {
+    /// Category name; `Parent/Child` names nest the category under `Parent`
     pub name: String,
-    pub patterns: Vec<String>,
+    /// Regexes on `nature_des_operations`, or rules
+    pub patterns: Vec<Pattern>,
     #[serde(default)]
     pub ignore: bool,
+    /// With `resolution: priority`, the assignment with the highest priority wins
+    #[serde(default)]
+    pub priority: i32,
+    /// Shares of the matched operations going to other categories, the rest
+    /// staying in this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub split: Vec<Share>,
+    /// Operations that must be assigned to this assignment
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<Example>,
+    /// Operations that must not be assigned to this assignment
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub counter_examples: Vec<Example>,
+}
+
+impl Assignment {
+    /// Allocate `montant` between the shares of `split` and this assignment.
+    /// Fixed amounts are taken first, then the percentages of what is left;
+    /// the parts always add up to `montant`.
+    pub fn allocate(&self, montant: i64) -> Vec<(String, i64)> {
+        let mut parts: Vec<(String, i64)> = Vec::new();
+        let mut remaining = montant;
+        for share in &self.split {
+            if let Some(amount) = share.amount {
+                let part = amount.clamp(0, remaining.max(0));
+                parts.push((share.name.clone(), part));
+                remaining -= part;
+            }
+        }
+
+        // Rounding the cumulated percentages loses no cent
+        let base = remaining;
+        let (mut cumulated, mut allocated) = (0.0, 0);
+        for share in &self.split {
+            if let (None, Some(percent)) = (share.amount, share.percent) {
+                cumulated += percent;
+                let boundary = ((base as f64 * cumulated / 100.0).round() as i64).clamp(0, base);
+                parts.push((share.name.clone(), boundary - allocated.min(boundary)));
+                allocated = allocated.max(boundary);
+            }
+        }
+        remaining = base - allocated;
+
+        parts.push((self.name.clone(), remaining));
+        parts.retain(|(_, part)| *part != 0);
+        if parts.is_empty() {
+            parts.push((self.name.clone(), montant));
+        }
+        parts
+    }
+}
+
+/// What to do with an operation matched by several assignments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    /// Fail with `VentilateError::MultipleMatch`
+    #[default]
+    Strict,
+    /// The assignment with the highest `priority` wins
+    Priority,
+    /// The matching pattern with the most literal characters in its regex
+    /// (`Pattern::specificity`) wins, whichever pattern of its assignment it is
+    MostSpecific,
+    /// The assignment that comes first in the spec wins
+    FirstInFile,
+}
+
+/// Order of the categories in the result of a ventilation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    /// The order of the assignments in the spec, each followed by its shares
+    #[default]
+    Spec,
+    /// Alphabetical order of the category names
+    Sorted,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct VentilationSpec {
     pub name: String,
+    /// Spending categories, matched against debit operations
     pub assignments: Vec<Assignment>,
+    /// Income categories (salary, refunds, rents received...), matched against credit operations
+    #[serde(default)]
+    pub income: Vec<Assignment>,
+    #[serde(default)]
+    pub resolution: Resolution,
+    #[serde(default)]
+    pub order: Order,
+    /// Operations assigned by hand before pattern matching, usually loaded
+    /// from an overrides file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<Override>,
+    /// Own accounts, the transfers between them being left out of the categories
+    #[serde(default, skip_serializing_if = "Transfers::is_empty")]
+    pub transfers: Transfers,
+}
+
+/// The section of the spec of a side: `assignments` for the debits, `income`
+/// for the credits
+pub fn section(montant_type: &SoldeType) -> &'static str {
+    match montant_type {
+        SoldeType::Debit => "assignments",
+        SoldeType::Credit => "income",
+    }
+}
+
+impl VentilationSpec {
+    /// The assignments debits (`assignments`) or credits (`income`) are matched against
+    pub fn side(&self, montant_type: &SoldeType) -> &[Assignment] {
+        match montant_type {
+            SoldeType::Debit => &self.assignments,
+            SoldeType::Credit => &self.income,
+        }
+    }
+
+    /// The categories of one side in the `Spec` order: the assignments, each
+    /// followed by the categories of its shares
+    pub fn categories(&self, montant_type: &SoldeType) -> IndexSet<String> {
+        let mut categories = IndexSet::new();
+        for assignment in self.side(montant_type) {
+            categories.insert(assignment.name.clone());
+            for share in &assignment.split {
+                categories.insert(share.name.clone());
+            }
+        }
+        categories
+    }
+
+    /// Every pattern whose regex does not compile, in the order of the spec
+    pub fn invalid_patterns(&self) -> Vec<InvalidPattern> {
+        let mut invalid = Vec::new();
+        for montant_type in [SoldeType::Debit, SoldeType::Credit] {
+            for assignment in self.side(&montant_type) {
+                for (index, pattern) in assignment.patterns.iter().enumerate() {
+                    let Some(regex) = pattern.regex() else {
+                        continue;
+                    };
+                    if let Err(e) = Regex::new(regex) {
+                        invalid.push(InvalidPattern {
+                            pattern: PatternRef {
+                                montant_type: montant_type.clone(),
+                                assignment: assignment.name.clone(),
+                                position: index + 1,
+                                pattern: regex.to_string(),
+                            },
+                            error: e.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        invalid
+    }
+
+    /// The shares of the `split` of every assignment that cannot be
+    /// allocated: with both or neither of `amount` and `percent`, negative, or
+    /// percentages adding up to more than 100
+    pub fn invalid_splits(&self) -> Vec<InvalidSplit> {
+        let mut invalid = Vec::new();
+        for montant_type in [SoldeType::Debit, SoldeType::Credit] {
+            for assignment in self.side(&montant_type) {
+                let mut error = |share: Option<&Share>, error: String| {
+                    invalid.push(InvalidSplit {
+                        montant_type: montant_type.clone(),
+                        assignment: assignment.name.clone(),
+                        share: share.map(|s| s.name.clone()),
+                        error,
+                    })
+                };
+                for share in &assignment.split {
+                    match (share.amount, share.percent) {
+                        (Some(_), Some(_)) => {
+                            error(Some(share), "both an amount and a percent".to_string())
+                        }
+                        (None, None) => {
+                            error(Some(share), "neither an amount nor a percent".to_string())
+                        }
+                        (Some(amount), None) if amount < 0 => {
+                            error(Some(share), format!("negative amount {amount}"))
+                        }
+                        (None, Some(percent)) if percent < 0.0 => {
+                            error(Some(share), format!("negative percent {percent}"))
+                        }
+                        _ => {}
+                    }
+                }
+                let percent: f64 = assignment.split.iter().filter_map(|s| s.percent).sum();
+                // 33.34 + 33.33 + 33.33 is a little over 100 in floating point
+                if percent > 100.0 + 1e-9 {
+                    error(None, format!("percentages add up to {percent}"));
+                }
+            }
+        }
+        invalid
+    }
+
+    /// Check that every regex of the spec compiles and that every `split`
+    /// can be allocated
+    pub fn validate(&self) -> Result<(), VentilateError> {
+        let invalid = self.invalid_patterns();
+        if !invalid.is_empty() {
+            return Err(VentilateError::InvalidPatterns(invalid));
+        }
+        self.validate_splits()
+    }
+
+    /// Check that every `split` of the spec can be allocated
+    pub fn validate_splits(&self) -> Result<(), VentilateError> {
+        let invalid = self.invalid_splits();
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(VentilateError::InvalidSplits(invalid))
+        }
+    }
+}
+
+/// A share of a `split` that cannot be allocated
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvalidSplit {
+    /// `Debit` for `assignments`, `Credit` for `income`
+    pub montant_type: SoldeType,
+    pub assignment: String,
+    /// Name of the share, `None` when the split as a whole is wrong
+    pub share: Option<String>,
+    pub error: String,
+}
+
+impl std::fmt::Display for InvalidSplit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let section = section(&self.montant_type);
+        match &self.share {
+            Some(share) => write!(
+                f,
+                "{section} '{}', share '{share}': {}",
+                self.assignment, self.error
+            ),
+            None => write!(f, "{section} '{}', split: {}", self.assignment, self.error),
+        }
+    }
+}
+
+/// A pattern of the spec: its side, assignment and position
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PatternRef {
+    /// `Debit` for `assignments`, `Credit` for `income`
+    pub montant_type: SoldeType,
+    pub assignment: String,
+    /// Position of the pattern in the assignment, starting at 1
+    pub position: usize,
+    pub pattern: String,
+}
+
+impl std::fmt::Display for PatternRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} '{}', pattern {}: '{}'",
+            section(&self.montant_type),
+            self.assignment,
+            self.position,
+            self.pattern
+        )
+    }
+}
+
+/// A pattern of the spec whose regex does not compile
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvalidPattern {
+    pub pattern: PatternRef,
+    pub error: String,
+}
+
+impl std::fmt::Display for InvalidPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)?;
+        for line in self.error.lines() {
+            write!(f, "\n    {line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An operation matched by several assignments, resolved by the `Resolution` of the spec
+#[derive(Debug, Clone, Serialize)]
+pub struct AmbiguousMatch {
+    pub date: NaiveDate,
+    pub operation: String,
+    pub montant: i64,
+    pub chosen: String,
+    pub matches: Vec<MatchInfo>,
+}
+
+/// Amounts and operations per assignment, for the debits or the credits.
+/// Categories are in the `order` of the spec.
+#[derive(Debug, Default, Serialize)]
+pub struct Breakdown {
+    pub ventilation: IndexMap<String, i64>,
+    /// Amounts rolled up at every level of the categories: `Transport` includes
+    /// `Transport/Train` and `Transport/Taxi`. Each category comes before its
+    /// sub-categories.
+    pub totals: IndexMap<String, i64>,
+    pub not_assigned: i64,
+    pub ventilated_operations: IndexMap<String, Vec<Operation>>,
+    pub not_assigned_operations: Vec<Operation>,
+    /// Internal transfers between own accounts, in no category
+    pub transfers: i64,
+    pub transfer_operations: Vec<Operation>,
+    /// Operations already listed by an earlier releve of the same account,
+    /// counted once
+    pub repeated: i64,
+    pub repeated_operations: Vec<Operation>,
+    /// Warnings for the operations matched by several assignments
+    pub ambiguous_matches: Vec<AmbiguousMatch>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct Ventilation {
     pub spec: VentilationSpec,
-    pub ventilation: HashMap<String, i64>,
-    pub not_assigned: i64,
-    pub ventilated_operations: HashMap<String, Vec<Operation>>,
-    pub not_assigned_operations: Vec<Operation>,
+    /// Money out, by `spec.assignments`
+    pub debit: Breakdown,
+    /// Money in, by `spec.income`
+    pub credit: Breakdown,
+    /// Internal transfers between the own accounts of `spec.transfers`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transfers: Vec<Transfer>,
 }
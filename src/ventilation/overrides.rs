@@ -0,0 +1,60 @@
+use crate::parser::model::{Operation, Releve, SoldeType};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+
+/// An operation put in a category by hand, whatever the patterns say.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Override {
+    /// `id` of the operation, or its `operation_key` for operations without one
+    pub id: String,
+    pub assignment: String,
+    /// Description of the operation, to make the file readable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl Override {
+    pub fn new(operation: &Operation, assignment: &str) -> Self {
+        Override {
+            id: operation
+                .id
+                .clone()
+                .unwrap_or_else(|| operation_key(operation)),
+            assignment: assignment.to_string(),
+            description: Some(operation.nature_des_operations.clone()),
+        }
+    }
+}
+
+/// Identifier of an operation without `id` in an overrides file: its date,
+/// signed amount in centimes and the start of the hash of its description, as
+/// in `2024-01-05:-5000:3f2a9c1e`.
+pub fn operation_key(operation: &Operation) -> String {
+    let signed = match operation.montant_type {
+        SoldeType::Debit => -operation.montant,
+        SoldeType::Credit => operation.montant,
+    };
+    let hash = format!(
+        "{:x}",
+        Sha256::digest(operation.nature_des_operations.as_bytes())
+    );
+    format!("{}:{signed}:{}", operation.date, &hash[..8])
+}
+
+/// The `operation_key`s shared by several operations without `id` of the
+/// releves, such as two identical coffees on the same day: an override with
+/// one of them would apply to all of these operations.
+pub fn duplicate_keys(data: &[Releve]) -> HashSet<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for operation in data.iter().flat_map(|r| &r.operations) {
+        if operation.id.is_none() {
+            *counts.entry(operation_key(operation)).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(key, _)| key)
+        .collect()
+}
@@ -0,0 +1,137 @@
+use super::model::{Breakdown, Ventilation};
+use crate::parser::model::Operation;
+use chrono::{Datelike, NaiveDate};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Length of the periods the operations are bucketed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Period {
+    /// Label of the period containing `date`: `2024-03`, `2024-Q1` or `2024`
+    pub fn label(&self, date: NaiveDate) -> String {
+        match self {
+            Period::Month => format!("{}-{:02}", date.year(), date.month()),
+            Period::Quarter => format!("{}-Q{}", date.year(), date.month0() / 3 + 1),
+            Period::Year => date.year().to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "month" => Ok(Period::Month),
+            "quarter" => Ok(Period::Quarter),
+            "year" => Ok(Period::Year),
+            _ => Err(format!(
+                "Unknown period {s}, expected month, quarter or year"
+            )),
+        }
+    }
+}
+
+/// Which date of an operation decides its period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateField {
+    Date,
+    Valeur,
+}
+
+impl DateField {
+    pub fn of(&self, operation: &Operation) -> NaiveDate {
+        match self {
+            DateField::Date => operation.date,
+            DateField::Valeur => operation.valeur,
+        }
+    }
+}
+
+impl std::str::FromStr for DateField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "date" => Ok(DateField::Date),
+            "valeur" => Ok(DateField::Valeur),
+            _ => Err(format!("Unknown date field {s}, expected date or valeur")),
+        }
+    }
+}
+
+/// Category × period amounts of one side (debits or credits), the categories
+/// in the order of the ventilation. Periods without operations in a category
+/// are left out.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct PeriodMatrix {
+    pub categories: IndexMap<String, BTreeMap<String, i64>>,
+    pub not_assigned: BTreeMap<String, i64>,
+    /// Total of each period, not assigned operations included
+    pub totals: BTreeMap<String, i64>,
+}
+
+impl PeriodMatrix {
+    fn add(&mut self, category: Option<&str>, period: String, montant: i64) {
+        let row = match category {
+            Some(name) => self.categories.entry(name.to_string()).or_default(),
+            None => &mut self.not_assigned,
+        };
+        *row.entry(period.clone()).or_insert(0) += montant;
+        *self.totals.entry(period).or_insert(0) += montant;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PeriodVentilation {
+    pub period: Period,
+    pub date_field: DateField,
+    /// Every period with at least one operation, in chronological order
+    pub periods: Vec<String>,
+    pub debit: PeriodMatrix,
+    pub credit: PeriodMatrix,
+}
+
+fn matrix(breakdown: &Breakdown, period: Period, date_field: DateField) -> PeriodMatrix {
+    let mut matrix = PeriodMatrix::default();
+    for (name, operations) in &breakdown.ventilated_operations {
+        for operation in operations {
+            let label = period.label(date_field.of(operation));
+            matrix.add(Some(name), label, operation.montant);
+        }
+    }
+    for operation in &breakdown.not_assigned_operations {
+        let label = period.label(date_field.of(operation));
+        matrix.add(None, label, operation.montant);
+    }
+    matrix
+}
+
+/// Bucket the result of `ventilate` by calendar month, quarter or year.
+pub fn ventilate_by_period(
+    ventilation: &Ventilation,
+    period: Period,
+    date_field: DateField,
+) -> PeriodVentilation {
+    let debit = matrix(&ventilation.debit, period, date_field);
+    let credit = matrix(&ventilation.credit, period, date_field);
+    // Labels sort chronologically
+    let periods: BTreeSet<&String> = debit.totals.keys().chain(credit.totals.keys()).collect();
+
+    PeriodVentilation {
+        period,
+        date_field,
+        periods: periods.into_iter().cloned().collect(),
+        debit,
+        credit,
+    }
+}
@@ -0,0 +1,291 @@
+use crate::parser::decompose;
+use crate::parser::model::{Operation, OperationKind, Releve, SoldeType};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+fn default_window_days() -> i64 {
+    3
+}
+
+/// One of our own accounts
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OwnAccount {
+    /// `account` of its releves
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iban: Option<String>,
+    /// Texts found in the descriptions of the transfers to or from this
+    /// account, as `LIVRET A`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+}
+
+impl OwnAccount {
+    /// Whether `account`, the account of a releve, is this one, by name or IBAN
+    fn is(&self, account: &str) -> bool {
+        account == self.name
+            || self
+                .iban
+                .as_deref()
+                .is_some_and(|iban| compact(iban) == compact(account))
+    }
+
+    /// Whether a description names this account, by IBAN or label
+    fn named_in(&self, description: &str) -> bool {
+        let upper = description.to_uppercase();
+        self.iban
+            .as_deref()
+            .is_some_and(|iban| compact(&upper).contains(&compact(iban)))
+            || self
+                .labels
+                .iter()
+                .any(|label| upper.contains(&label.to_uppercase()))
+    }
+}
+
+/// Whether an operation is a transfer by its kind, read from its description
+/// when it has no details
+fn is_virement(operation: &Operation) -> bool {
+    let kind = match &operation.details {
+        Some(details) => details.kind.clone(),
+        None => decompose(&operation.nature_des_operations).kind,
+    };
+    matches!(
+        kind,
+        OperationKind::VirementEmis | OperationKind::VirementRecu
+    )
+}
+
+/// An IBAN without its spaces, in capitals
+fn compact(iban: &str) -> String {
+    iban.split_whitespace().collect::<String>().to_uppercase()
+}
+
+/// Our own accounts, between which money moves without being spent or earned.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Transfers {
+    #[serde(default)]
+    pub accounts: Vec<OwnAccount>,
+    /// Maximum number of days between the debit and the credit of a transfer
+    #[serde(default = "default_window_days")]
+    pub window_days: i64,
+}
+
+impl Default for Transfers {
+    fn default() -> Self {
+        Transfers {
+            accounts: Vec::new(),
+            window_days: default_window_days(),
+        }
+    }
+}
+
+impl Transfers {
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// The own account a releve belongs to
+    fn own_account(&self, account: Option<&str>) -> Option<usize> {
+        let account = account?;
+        self.accounts.iter().position(|a| a.is(account))
+    }
+}
+
+/// One side of an internal transfer
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferLeg {
+    /// `account` of the releve
+    pub account: Option<String>,
+    pub operation: Operation,
+    /// Index of the releve and of the operation in it
+    #[serde(skip)]
+    pub position: (usize, usize),
+}
+
+/// Money moved from one of our accounts to another. Both legs are found when
+/// both accounts are imported; otherwise the description of the only leg
+/// names the other account.
+#[derive(Debug, Clone, Serialize)]
+pub struct Transfer {
+    pub montant: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outgoing: Option<TransferLeg>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incoming: Option<TransferLeg>,
+    /// The own account named in the description of a single leg
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counterpart: Option<String>,
+}
+
+impl Transfer {
+    /// Name of the account the money leaves, if known
+    pub fn from(&self) -> Option<&str> {
+        match (&self.outgoing, &self.incoming) {
+            (Some(leg), _) => leg.account.as_deref(),
+            (None, Some(_)) => self.counterpart.as_deref(),
+            (None, None) => None,
+        }
+    }
+
+    /// Name of the account the money goes to, if known
+    pub fn to(&self) -> Option<&str> {
+        match (&self.outgoing, &self.incoming) {
+            (_, Some(leg)) => leg.account.as_deref(),
+            (Some(_), None) => self.counterpart.as_deref(),
+            (None, None) => None,
+        }
+    }
+
+    /// Positions of the operations of the transfer in the releves
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.outgoing
+            .iter()
+            .chain(&self.incoming)
+            .map(|leg| leg.position)
+    }
+
+    /// Name of the other account of the operation at `position`, if known:
+    /// where the money goes for the outgoing leg, where it comes from for the
+    /// incoming one
+    pub fn other_account(&self, position: (usize, usize)) -> Option<&str> {
+        match &self.outgoing {
+            Some(leg) if leg.position == position => self.to(),
+            _ => self.from(),
+        }
+    }
+}
+
+/// An operation that may be a leg of a transfer: the own account of its
+/// releve, its position in the releves, the operation and whether it is a
+/// transfer by its kind
+type Candidate<'a> = (Option<usize>, (usize, usize), &'a Operation, bool);
+
+/// Find the internal transfers between the own accounts of `config`.
+///
+/// Each debit from one own account, in chronological order, is paired with the
+/// closest credit of the same amount to another one, at most `window_days`
+/// apart. Both must be transfers by their kind (`VirementEmis`,
+/// `VirementRecu`) or name the other account in their description, so that a
+/// card payment and an unrelated refund of the same amount are not paired. The operations left whose description names another own account are
+/// transfers to or from an account whose releves are not imported.
+pub fn find_transfers(data: &[Releve], config: &Transfers) -> Vec<Transfer> {
+    if config.is_empty() {
+        return Vec::new();
+    }
+    let leg = |position: (usize, usize)| TransferLeg {
+        account: data[position.0].account.clone(),
+        operation: data[position.0].operations[position.1].clone(),
+        position,
+    };
+
+    let mut debits: Vec<Candidate> = Vec::new();
+    let mut credits: Vec<Candidate> = Vec::new();
+    for (r, releve) in data.iter().enumerate() {
+        let own = config.own_account(releve.account.as_deref());
+        for (o, operation) in releve.operations.iter().enumerate() {
+            let candidates = match operation.montant_type {
+                SoldeType::Debit => &mut debits,
+                SoldeType::Credit => &mut credits,
+            };
+            let virement = own.is_some() && is_virement(operation);
+            candidates.push((own, (r, o), operation, virement));
+        }
+    }
+    debits.sort_by_key(|(_, position, op, _)| (op.date, *position));
+    // Credits to an own account by amount, in chronological order
+    let mut by_montant: HashMap<i64, Vec<Candidate>> = HashMap::new();
+    for &credit in &credits {
+        if credit.0.is_some() {
+            by_montant.entry(credit.2.montant).or_default().push(credit);
+        }
+    }
+    for candidates in by_montant.values_mut() {
+        candidates.sort_by_key(|(_, position, op, _)| (op.date, *position));
+    }
+
+    let mut transfers = Vec::new();
+    let mut paired: HashSet<(usize, usize)> = HashSet::new();
+    // A leg is a transfer by its kind or by naming the other account
+    let leg_of = |virement: bool, operation: &Operation, other: usize| {
+        virement || config.accounts[other].named_in(&operation.nature_des_operations)
+    };
+    for &(from, debit_position, debit, debit_virement) in &debits {
+        let Some(from) = from else {
+            continue;
+        };
+        let Some(candidates) = by_montant.get(&debit.montant) else {
+            continue;
+        };
+        let window = Duration::days(config.window_days);
+        let start =
+            candidates.partition_point(|(_, _, credit, _)| credit.date < debit.date - window);
+        let credit = candidates[start..]
+            .iter()
+            .take_while(|(_, _, credit, _)| credit.date <= debit.date + window)
+            .filter(|&&(to, position, credit, credit_virement)| {
+                let Some(to) = to.filter(|to| *to != from) else {
+                    return false;
+                };
+                !paired.contains(&position)
+                    && leg_of(debit_virement, debit, to)
+                    && leg_of(credit_virement, credit, from)
+            })
+            .min_by_key(|(_, position, credit, _)| {
+                ((credit.date - debit.date).num_days().abs(), *position)
+            });
+        if let Some(&(_, credit_position, _, _)) = credit {
+            paired.insert(debit_position);
+            paired.insert(credit_position);
+            transfers.push(Transfer {
+                montant: debit.montant,
+                outgoing: Some(leg(debit_position)),
+                incoming: Some(leg(credit_position)),
+                counterpart: None,
+            });
+        }
+    }
+
+    // Single legs, to or from an account not imported
+    for &(own, position, operation, _) in debits.iter().chain(&credits) {
+        if paired.contains(&position) {
+            continue;
+        }
+        let counterpart = config.accounts.iter().enumerate().find(|(index, account)| {
+            own != Some(*index) && account.named_in(&operation.nature_des_operations)
+        });
+        if let Some((_, account)) = counterpart {
+            let (outgoing, incoming) = match operation.montant_type {
+                SoldeType::Debit => (Some(leg(position)), None),
+                SoldeType::Credit => (None, Some(leg(position))),
+            };
+            transfers.push(Transfer {
+                montant: operation.montant,
+                outgoing,
+                incoming,
+                counterpart: Some(account.name.clone()),
+            });
+        }
+    }
+
+    transfers.sort_by_key(|t| {
+        let first = t.outgoing.as_ref().or(t.incoming.as_ref());
+        first.map(|leg| (leg.operation.date, leg.position))
+    });
+    transfers
+}
+
+/// Positions of the operations of `transfers` in the releves
+pub fn transfer_positions(transfers: &[Transfer]) -> HashSet<(usize, usize)> {
+    transfers.iter().flat_map(Transfer::positions).collect()
+}
+
+/// The transfer the operation at `position` in the releves is a leg of
+pub fn transfer_at(transfers: &[Transfer], position: (usize, usize)) -> Option<&Transfer> {
+    transfers
+        .iter()
+        .find(|t| t.positions().any(|p| p == position))
+}
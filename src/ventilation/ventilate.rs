@@ -1,9 +1,14 @@
-use super::model::{Assignment, Ventilation, VentilationSpec};
-use crate::parser::model::{Operation, Releve, SoldeType};
-use regex::Regex;
-use std::collections::HashMap;
+use super::compiled::CompiledSpec;
+use super::model::{
+    AmbiguousMatch, Breakdown, InvalidPattern, InvalidSplit, Order, Ventilation, VentilationSpec,
+    category_ancestors, category_levels, section,
+};
+use super::transfers::{find_transfers, transfer_positions};
+use crate::parser::model::{Operation, Releve, SoldeType, repeated_operations};
+use serde::Serialize;
+use std::collections::HashSet;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MatchInfo {
     pub assignment: String,
     pub pattern: String,
@@ -16,9 +21,20 @@ pub enum VentilateError {
         matches: Vec<MatchInfo>,
     },
     SumMismatch {
+        montant_type: SoldeType,
         expected: i64,
         actual: i64,
     },
+    /// Regexes of the spec that do not compile
+    InvalidPatterns(Vec<InvalidPattern>),
+    /// Shares of a `split` that cannot be allocated
+    InvalidSplits(Vec<InvalidSplit>),
+    /// The regexes of one side compile one by one but not together, as when
+    /// they exceed the size limit of a `RegexSet`
+    RegexSet {
+        montant_type: SoldeType,
+        error: String,
+    },
 }
 
 impl std::fmt::Display for VentilateError {
@@ -31,10 +47,42 @@ impl std::fmt::Display for VentilateError {
                 }
                 Ok(())
             }
-            VentilateError::SumMismatch { expected, actual } => {
+            VentilateError::SumMismatch {
+                montant_type,
+                expected,
+                actual,
+            } => {
+                let total = match montant_type {
+                    SoldeType::Debit => "total_des_operations_debit",
+                    SoldeType::Credit => "total_des_operations_credit",
+                };
+                write!(
+                    f,
+                    "Sum mismatch: expected {expected} ({total}), got {actual} (ventilation + not_assigned + transfers)"
+                )
+            }
+            VentilateError::InvalidPatterns(invalid) => {
+                writeln!(f, "Invalid patterns in the ventilation spec:")?;
+                for pattern in invalid {
+                    writeln!(f, "  - {pattern}")?;
+                }
+                Ok(())
+            }
+            VentilateError::InvalidSplits(invalid) => {
+                writeln!(f, "Invalid splits in the ventilation spec:")?;
+                for split in invalid {
+                    writeln!(f, "  - {split}")?;
+                }
+                Ok(())
+            }
+            VentilateError::RegexSet {
+                montant_type,
+                error,
+            } => {
                 write!(
                     f,
-                    "Sum mismatch: expected {expected} (total_des_operations_debit), got {actual} (ventilation + not_assigned)"
+                    "The patterns of the {} cannot be compiled together: {error}",
+                    section(montant_type)
                 )
             }
         }
@@ -43,68 +91,160 @@ impl std::fmt::Display for VentilateError {
 
 impl std::error::Error for VentilateError {}
 
-fn find_matching_assignment(operation: &Operation, assignments: &[Assignment]) -> Vec<MatchInfo> {
-    let mut matches = Vec::new();
-    for assignment in assignments {
-        for pattern in &assignment.patterns {
-            if let Ok(re) = Regex::new(pattern) {
-                if re.is_match(&operation.nature_des_operations) {
-                    matches.push(MatchInfo {
-                        assignment: assignment.name.clone(),
-                        pattern: pattern.clone(),
-                    });
-                    break;
-                }
-            }
+/// Put the categories of a breakdown in the `order` of the spec, and roll
+/// them up into its `totals`.
+fn order_categories(breakdown: &mut Breakdown, spec: &VentilationSpec, montant_type: &SoldeType) {
+    match spec.order {
+        Order::Spec => {
+            // Categories only found in overrides come last
+            let categories = spec.categories(montant_type);
+            let key = |name: &String| {
+                (
+                    categories.get_index_of(name).unwrap_or(usize::MAX),
+                    name.clone(),
+                )
+            };
+            breakdown
+                .ventilation
+                .sort_by_cached_key(|name, _| key(name));
+            breakdown
+                .ventilated_operations
+                .sort_by_cached_key(|name, _| key(name));
+        }
+        Order::Sorted => {
+            let key = |name: &String| category_levels(name).map(String::from).collect::<Vec<_>>();
+            breakdown
+                .ventilation
+                .sort_by_cached_key(|name, _| key(name));
+            breakdown
+                .ventilated_operations
+                .sort_by_cached_key(|name, _| key(name));
         }
     }
-    matches
+
+    for (name, amount) in &breakdown.ventilation {
+        for category in category_ancestors(name) {
+            *breakdown.totals.entry(category).or_insert(0) += amount;
+        }
+    }
+    // Each category right before its sub-categories, in the order they were met
+    let met = breakdown.totals.clone();
+    breakdown.totals.sort_by_cached_key(|name, _| {
+        category_ancestors(name)
+            .iter()
+            .map(|ancestor| met.get_index_of(ancestor))
+            .collect::<Vec<_>>()
+    });
 }
 
-pub fn ventilate(spec: VentilationSpec, data: &[Releve]) -> Result<Ventilation, VentilateError> {
-    let mut ventilation_map: HashMap<String, i64> = HashMap::new();
-    let mut ventilated_operations: HashMap<String, Vec<Operation>> = HashMap::new();
-    let mut not_assigned: i64 = 0;
-    let mut not_assigned_operations: Vec<Operation> = Vec::new();
+/// Ventilate the operations of one side (debits or credits), checking the
+/// sum against the totals of the releves.
+fn ventilate_side(
+    compiled: &CompiledSpec,
+    data: &[Releve],
+    transfers: &HashSet<(usize, usize)>,
+    repeated: &HashSet<(usize, usize)>,
+    montant_type: SoldeType,
+) -> Result<Breakdown, VentilateError> {
+    let mut breakdown = Breakdown::default();
 
-    for releve in data {
-        for operation in &releve.operations {
-            if let SoldeType::Debit = operation.montant_type {
-                let matches = find_matching_assignment(operation, &spec.assignments);
-                if matches.len() > 1 {
-                    return Err(VentilateError::MultipleMatch {
-                        operation: operation.nature_des_operations.clone(),
-                        matches,
-                    });
-                }
-                if let Some(match_info) = matches.first() {
-                    *ventilation_map
-                        .entry(match_info.assignment.clone())
-                        .or_insert(0) += operation.montant;
-                    ventilated_operations
-                        .entry(match_info.assignment.clone())
+    for (r, releve) in data.iter().enumerate() {
+        for (o, operation) in releve.operations.iter().enumerate() {
+            if operation.montant_type != montant_type {
+                continue;
+            }
+            if repeated.contains(&(r, o)) {
+                breakdown.repeated += operation.montant;
+                breakdown.repeated_operations.push(operation.clone());
+                continue;
+            }
+            if transfers.contains(&(r, o)) {
+                breakdown.transfers += operation.montant;
+                breakdown.transfer_operations.push(operation.clone());
+                continue;
+            }
+            let (assigned, matches) = compiled.assign(operation, releve.account.as_deref())?;
+            if let Some(match_info) = &assigned
+                && !matches.is_empty()
+            {
+                breakdown.ambiguous_matches.push(AmbiguousMatch {
+                    date: operation.date,
+                    operation: operation.nature_des_operations.clone(),
+                    montant: operation.montant,
+                    chosen: match_info.assignment.clone(),
+                    matches,
+                });
+            }
+            if let Some(match_info) = assigned {
+                // A split operation is listed in each category with its share
+                for (name, part) in compiled.allocation(&match_info, operation) {
+                    *breakdown.ventilation.entry(name.clone()).or_insert(0) += part;
+                    breakdown
+                        .ventilated_operations
+                        .entry(name)
                         .or_default()
-                        .push(operation.clone());
-                } else {
-                    not_assigned += operation.montant;
-                    not_assigned_operations.push(operation.clone());
+                        .push(Operation {
+                            montant: part,
+                            ..operation.clone()
+                        });
                 }
+            } else {
+                breakdown.not_assigned += operation.montant;
+                breakdown.not_assigned_operations.push(operation.clone());
             }
         }
     }
 
-    let expected: i64 = data.iter().map(|r| r.total_des_operations_debit).sum();
-    let actual: i64 = ventilation_map.values().sum::<i64>() + not_assigned;
+    order_categories(&mut breakdown, compiled.spec(), &montant_type);
+
+    let expected: i64 = data
+        .iter()
+        .map(|r| match montant_type {
+            SoldeType::Debit => r.total_des_operations_debit,
+            SoldeType::Credit => r.total_des_operations_credit,
+        })
+        .sum();
+    let actual: i64 = breakdown.ventilation.values().sum::<i64>()
+        + breakdown.not_assigned
+        + breakdown.transfers
+        + breakdown.repeated;
 
     if expected != actual {
-        return Err(VentilateError::SumMismatch { expected, actual });
+        return Err(VentilateError::SumMismatch {
+            montant_type,
+            expected,
+            actual,
+        });
     }
 
+    Ok(breakdown)
+}
+
+/// Ventilate debits over the assignments of `spec` and credits over its income
+/// assignments, leaving out the transfers between its own accounts and the
+/// operations of overlapping releves after their first one. Invalid
+/// regexes in the spec and operations matched by several assignments are
+/// errors, unless the spec has another `resolution`.
+pub fn ventilate(spec: VentilationSpec, data: &[Releve]) -> Result<Ventilation, VentilateError> {
+    ventilate_compiled(&CompiledSpec::new(spec)?, data)
+}
+
+/// `ventilate` with a spec compiled beforehand, to ventilate several sets of
+/// releves with the same spec.
+pub fn ventilate_compiled(
+    compiled: &CompiledSpec,
+    data: &[Releve],
+) -> Result<Ventilation, VentilateError> {
+    let transfers = find_transfers(data, &compiled.spec().transfers);
+    let positions = transfer_positions(&transfers);
+    let repeated = repeated_operations(data);
+    let debit = ventilate_side(compiled, data, &positions, &repeated, SoldeType::Debit)?;
+    let credit = ventilate_side(compiled, data, &positions, &repeated, SoldeType::Credit)?;
+
     Ok(Ventilation {
-        spec,
-        ventilation: ventilation_map,
-        not_assigned,
-        ventilated_operations,
-        not_assigned_operations,
+        spec: compiled.spec().clone(),
+        debit,
+        credit,
+        transfers,
     })
 }
@@ -0,0 +1,133 @@
+use chrono::NaiveDate;
+use my_bank_statements::export::to_camt053;
+use my_bank_statements::import::{ImportError, parse_camt053};
+use my_bank_statements::{Operation, Releve, Solde, SoldeType};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+const CAMT053: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>MSG1</MsgId><CreDtTm>2024-02-01T06:00:00</CreDtTm></GrpHdr>
+    <Stmt>
+      <Id>STMT1</Id>
+      <Acct><Id><IBAN>FR7630004000031234567890143</IBAN></Id></Acct>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">100.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Dt><Dt>2024-01-01</Dt></Dt>
+      </Bal>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">1854.50</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Dt><Dt>2024-01-31</Dt></Dt>
+      </Bal>
+      <TxsSummry>
+        <TtlCdtNtries><NbOfNtries>1</NbOfNtries><Sum>2000.00</Sum></TtlCdtNtries>
+        <TtlDbtNtries><NbOfNtries>1</NbOfNtries><Sum>45.50</Sum></TtlDbtNtries>
+      </TxsSummry>
+      <Ntry>
+        <Amt Ccy="EUR">45.50</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><Dt>2024-01-10</Dt></BookgDt>
+        <ValDt><Dt>2024-01-09</Dt></ValDt>
+        <NtryDtls><TxDtls><RmtInf><Ustrd>PRLV SEPA EDF</Ustrd></RmtInf></TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">2000.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><DtTm>2024-01-25T10:00:00</DtTm></BookgDt>
+        <AddtlNtryInf>VIR SEPA RECU ACME &amp; CO</AddtlNtryInf>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>
+"#;
+
+#[test]
+fn test_parse_camt053() {
+    let releves = parse_camt053(CAMT053).unwrap();
+    assert_eq!(releves.len(), 1);
+    let releve = &releves[0];
+
+    assert_eq!(releve.date_du_releve, date(2024, 1, 31));
+    assert!(matches!(
+        releve.solde_ouverture.solde_type,
+        SoldeType::Debit
+    ));
+    assert_eq!(releve.solde_ouverture.montant, 10000);
+    assert_eq!(releve.solde_cloture.montant, 185450);
+    assert_eq!(releve.total_des_operations_debit, 4550);
+    assert_eq!(releve.total_des_operations_credit, 200000);
+    assert_eq!(releve.check_debit, 4550);
+
+    let ops = &releve.operations;
+    assert_eq!(ops[0].nature_des_operations, "PRLV SEPA EDF");
+    assert_eq!(ops[0].valeur, date(2024, 1, 9));
+    assert!(matches!(ops[0].montant_type, SoldeType::Debit));
+    assert_eq!(ops[1].nature_des_operations, "VIR SEPA RECU ACME & CO");
+    assert_eq!(ops[1].date, date(2024, 1, 25));
+    assert_eq!(ops[1].valeur, date(2024, 1, 25));
+}
+
+#[test]
+fn test_camt053_without_closing_balance() {
+    let text = CAMT053.replace("CLBD", "CLAV");
+
+    assert!(matches!(
+        parse_camt053(&text),
+        Err(ImportError::Format { .. })
+    ));
+}
+
+#[test]
+fn test_camt053_round_trip() {
+    let releve = Releve {
+        date_du_releve: date(2024, 2, 13),
+        solde_ouverture: Solde {
+            solde_type: SoldeType::Credit,
+            montant: 50000,
+        },
+        solde_cloture: Solde {
+            solde_type: SoldeType::Credit,
+            montant: 48765,
+        },
+        total_des_operations_debit: 1235,
+        total_des_operations_credit: 0,
+        check_debit: 1235,
+        check_credit: 0,
+        operations: vec![Operation {
+            date: date(2024, 2, 1),
+            nature_des_operations: "FACTURE CARTE DU 300124 <CAFE> & CIE".to_string(),
+            valeur: date(2024, 2, 2),
+            montant: 1235,
+            montant_type: SoldeType::Debit,
+            ..Default::default()
+        }],
+        account: None,
+    };
+
+    let xml = to_camt053(std::slice::from_ref(&releve), "FR7630004000031234567890143");
+    assert!(xml.contains("<Cd>OPBD</Cd>"));
+    assert!(xml.contains("<Amt Ccy=\"EUR\">12.35</Amt>"));
+
+    let back = parse_camt053(&xml).unwrap();
+    assert_eq!(back.len(), 1);
+    let back = &back[0];
+    assert_eq!(back.date_du_releve, releve.date_du_releve);
+    assert_eq!(back.account.as_deref(), Some("FR7630004000031234567890143"));
+    assert_eq!(back.solde_ouverture.montant, 50000);
+    assert_eq!(back.solde_cloture.montant, 48765);
+    assert_eq!(back.total_des_operations_debit, 1235);
+    assert_eq!(back.operations.len(), 1);
+    assert_eq!(
+        back.operations[0].nature_des_operations,
+        releve.operations[0].nature_des_operations
+    );
+    assert_eq!(back.operations[0].valeur, date(2024, 2, 2));
+    assert!(matches!(back.operations[0].montant_type, SoldeType::Debit));
+}
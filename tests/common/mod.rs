@@ -0,0 +1,72 @@
+//! Operations and releves shared by the integration tests
+#![allow(dead_code)]
+
+use chrono::NaiveDate;
+use my_bank_statements::parser::model::{Operation, Releve, Solde, SoldeType};
+
+pub fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+/// An operation valued on the day it is made
+pub fn operation(
+    date: NaiveDate,
+    nature: &str,
+    montant: i64,
+    montant_type: SoldeType,
+) -> Operation {
+    Operation {
+        date,
+        nature_des_operations: nature.to_string(),
+        valeur: date,
+        montant,
+        montant_type,
+        ..Default::default()
+    }
+}
+
+pub fn debit(date: NaiveDate, nature: &str, montant: i64) -> Operation {
+    operation(date, nature, montant, SoldeType::Debit)
+}
+
+pub fn credit(date: NaiveDate, nature: &str, montant: i64) -> Operation {
+    operation(date, nature, montant, SoldeType::Credit)
+}
+
+/// A releve opening at `ouverture`, its totals and closing balance computed
+/// from its operations
+pub fn releve(date_du_releve: NaiveDate, ouverture: i64, operations: Vec<Operation>) -> Releve {
+    let total = |montant_type: SoldeType| -> i64 {
+        operations
+            .iter()
+            .filter(|op| op.montant_type == montant_type)
+            .map(|op| op.montant)
+            .sum()
+    };
+    let (debit, credit) = (total(SoldeType::Debit), total(SoldeType::Credit));
+    Releve {
+        date_du_releve,
+        solde_ouverture: Solde {
+            solde_type: SoldeType::Credit,
+            montant: ouverture,
+        },
+        solde_cloture: Solde {
+            solde_type: SoldeType::Credit,
+            montant: ouverture + credit - debit,
+        },
+        total_des_operations_debit: debit,
+        total_des_operations_credit: credit,
+        check_debit: debit,
+        check_credit: credit,
+        operations,
+        account: None,
+    }
+}
+
+/// `releve` as a releve of `account`
+pub fn in_account(account: &str, releve: Releve) -> Releve {
+    Releve {
+        account: Some(account.to_string()),
+        ..releve
+    }
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::{date, debit, releve};
+use my_bank_statements::{ContinuityIssue, check_continuity};
+
+#[test]
+fn test_continuous_releves() {
+    let releves = vec![
+        releve(
+            date(2024, 1, 13),
+            100000,
+            vec![debit(date(2024, 1, 5), "PRLV SEPA EDF", 5000)],
+        ),
+        releve(
+            date(2024, 2, 13),
+            95000,
+            vec![debit(date(2024, 2, 5), "PRLV SEPA EDF", 5000)],
+        ),
+        releve(date(2024, 3, 13), 90000, vec![]),
+    ];
+
+    assert_eq!(check_continuity(&releves), vec![]);
+}
+
+#[test]
+fn test_missing_month_and_balance_break() {
+    let releves = vec![
+        releve(date(2024, 4, 13), 80000, vec![]),
+        releve(
+            date(2024, 1, 13),
+            100000,
+            vec![debit(date(2024, 1, 5), "PRLV SEPA EDF", 5000)],
+        ),
+    ];
+
+    let issues = check_continuity(&releves);
+
+    assert_eq!(
+        issues,
+        vec![
+            ContinuityIssue::MissingMonths {
+                previous: date(2024, 1, 13),
+                next: date(2024, 4, 13),
+                months: vec![(2024, 2), (2024, 3)],
+            },
+            ContinuityIssue::BalanceBreak {
+                previous: date(2024, 1, 13),
+                next: date(2024, 4, 13),
+                cloture: 95000,
+                ouverture: 80000,
+                gap: -15000,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_missing_month_across_years() {
+    let releves = vec![
+        releve(date(2023, 11, 13), 100000, vec![]),
+        releve(date(2024, 1, 13), 100000, vec![]),
+    ];
+
+    match &check_continuity(&releves)[..] {
+        [ContinuityIssue::MissingMonths { months, .. }] => {
+            assert_eq!(months, &vec![(2023, 12)]);
+        }
+        issues => panic!("Expected MissingMonths, got {issues:?}"),
+    }
+}
+
+#[test]
+fn test_overlapping_releves() {
+    let releves = vec![
+        releve(
+            date(2024, 1, 13),
+            100000,
+            vec![debit(date(2024, 1, 5), "PRLV SEPA EDF", 5000)],
+        ),
+        releve(
+            date(2024, 2, 13),
+            95000,
+            vec![debit(date(2024, 1, 10), "PRLV SEPA EDF", 5000)],
+        ),
+    ];
+
+    let issues = check_continuity(&releves);
+    assert_eq!(
+        issues,
+        vec![ContinuityIssue::Overlap {
+            previous: date(2024, 1, 13),
+            next: date(2024, 2, 13),
+            operations: 1,
+        }]
+    );
+}
+
+#[test]
+fn test_inconsistent_releve() {
+    let mut inconsistent = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![debit(date(2024, 1, 5), "PRLV SEPA EDF", 5000)],
+    );
+    inconsistent.solde_cloture.montant = 96000;
+
+    let issues = check_continuity(&[inconsistent]);
+    assert_eq!(
+        issues,
+        vec![ContinuityIssue::InconsistentReleve {
+            date_du_releve: date(2024, 1, 13),
+            expected_cloture: 95000,
+            actual_cloture: 96000,
+            gap: 1000,
+        }]
+    );
+    assert!(format!("{}", issues[0]).contains("gap 10.00"));
+}
@@ -0,0 +1,65 @@
+use chrono::NaiveDate;
+use my_bank_statements::parser::{OperationKind, decompose};
+
+#[test]
+fn test_card_payment() {
+    let details = decompose("FACTURE(S) CARTE 4974XXXXXXXX1234 DU 120124 CARREFOUR MARKET PARIS");
+
+    assert_eq!(details.kind, OperationKind::CarteBancaire);
+    assert_eq!(details.card_suffix.as_deref(), Some("1234"));
+    assert_eq!(details.purchase_date, NaiveDate::from_ymd_opt(2024, 1, 12));
+    assert_eq!(details.merchant.as_deref(), Some("CARREFOUR MARKET PARIS"));
+}
+
+#[test]
+fn test_card_payment_short_mask() {
+    let details = decompose("FACTURE(S) CARTE 4974XXXX DU 311223 SNCF");
+
+    assert_eq!(details.card_suffix.as_deref(), Some("4974XXXX"));
+    assert_eq!(details.purchase_date, NaiveDate::from_ymd_opt(2023, 12, 31));
+    assert_eq!(details.merchant.as_deref(), Some("SNCF"));
+}
+
+#[test]
+fn test_sepa_direct_debit() {
+    let details = decompose(
+        "PRLV SEPA EDF CLIENTS PARTICULIERS ECH/150124 ID EMETTEUR/FR19ZZZ001234 MDT/ABC-123 REF/987654 LIB/FACTURE",
+    );
+
+    assert_eq!(details.kind, OperationKind::PrelevementSepa);
+    assert_eq!(
+        details.counterparty.as_deref(),
+        Some("EDF CLIENTS PARTICULIERS")
+    );
+    assert_eq!(details.creditor_id.as_deref(), Some("FR19ZZZ001234"));
+    assert_eq!(details.mandate_reference.as_deref(), Some("ABC-123"));
+}
+
+#[test]
+fn test_transfers() {
+    let received = decompose("VIR SEPA RECU /DE ACME SAS /MOTIF SALAIRE JANVIER /REF 42");
+    assert_eq!(received.kind, OperationKind::VirementRecu);
+    assert_eq!(received.counterparty.as_deref(), Some("ACME SAS"));
+
+    let sent = decompose("VIR SEPA EMIS /MOTIF LOYER /BEN JEAN DUPONT /REFDO 1");
+    assert_eq!(sent.kind, OperationKind::VirementEmis);
+    assert_eq!(sent.counterparty.as_deref(), Some("JEAN DUPONT"));
+}
+
+#[test]
+fn test_other_kinds() {
+    assert_eq!(
+        decompose("RETRAIT DAB CARTE 4974XXXXXXXX1234 DU 050124 PARIS").kind,
+        OperationKind::RetraitDab
+    );
+    assert_eq!(decompose("CHEQUE 1234567").kind, OperationKind::Cheque);
+    assert_eq!(
+        decompose("REMISE CHEQUES 0001234").kind,
+        OperationKind::RemiseCheques
+    );
+    assert_eq!(
+        decompose("* COMMISSIONS FRAIS DE TENUE").kind,
+        OperationKind::Commission
+    );
+    assert_eq!(decompose("ECHEANCE PRET").kind, OperationKind::Autre);
+}
@@ -0,0 +1,114 @@
+use my_bank_statements::parser::model::SoldeType;
+use my_bank_statements::ventilation::compiled::CompiledSpec;
+use my_bank_statements::ventilation::examples::check_examples;
+use my_bank_statements::ventilation::model::{Example, VentilationSpec};
+
+fn compile(yaml: &str) -> CompiledSpec {
+    let spec: VentilationSpec = serde_yaml::from_str(yaml).unwrap();
+    CompiledSpec::new(spec).unwrap()
+}
+
+#[test]
+fn test_examples_pass() {
+    let compiled = compile(
+        "
+name: Test
+resolution: priority
+assignments:
+- name: Courses
+  patterns: [CARREFOUR]
+  examples: [CARREFOUR CITY]
+  counter_examples: [CARREFOUR LOCATION]
+- name: Location
+  priority: 10
+  patterns:
+  - CARREFOUR LOCATION
+  - pattern: ^LOC
+    min_amount: 10000
+  examples:
+  - CARREFOUR LOCATION
+  - description: LOC UTILITAIRE
+    montant: 15000
+income:
+- name: Salaire
+  patterns: [SALAIRE]
+  examples: [VIR SALAIRE ACME]
+",
+    );
+    assert_eq!(check_examples(&compiled), vec![]);
+}
+
+#[test]
+fn test_examples_fail() {
+    let compiled = compile(
+        "
+name: Test
+assignments:
+- name: Courses
+  patterns: [CARREFOUR, LOCATION]
+  examples: [CARREFOUR CITY, MONOPRIX]
+- name: Location
+  patterns: [LOCATION]
+  examples:
+  - CARREFOUR LOCATION
+  - description: LOC UTILITAIRE
+    montant: 15000
+  counter_examples: [LOCATION VELO]
+",
+    );
+    let failures = check_examples(&compiled);
+    let found: Vec<(&str, &str, bool, Option<&str>, usize)> = failures
+        .iter()
+        .map(|f| {
+            (
+                f.assignment.as_str(),
+                f.description.as_str(),
+                f.expected_match,
+                f.assigned.as_deref(),
+                f.matches.len(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("Courses", "MONOPRIX", true, None, 0),
+            // Strict resolution: matched by both assignments
+            ("Location", "CARREFOUR LOCATION", true, None, 2),
+            ("Location", "LOC UTILITAIRE", true, None, 0),
+            ("Location", "LOCATION VELO", false, None, 2),
+        ]
+    );
+    assert!(failures.iter().all(|f| f.montant_type == SoldeType::Debit));
+    assert_eq!(
+        failures[3].to_string(),
+        "assignments 'Location', counter example 'LOCATION VELO':
+  - not assigned to Location
+  + matched by several assignments
+  + matched by Courses (pattern: LOCATION)
+  + matched by Location (pattern: LOCATION)
+"
+    );
+}
+
+#[test]
+fn test_example_operation() {
+    let example: Example = serde_yaml::from_str(
+        "
+description: SNCF
+montant: 12000
+date: 2024-03-01
+account: joint
+",
+    )
+    .unwrap();
+    let operation = example.operation(SoldeType::Debit);
+    assert_eq!(operation.nature_des_operations, "SNCF");
+    assert_eq!(operation.montant, 12000);
+    assert_eq!(operation.date.to_string(), "2024-03-01");
+    assert_eq!(example.account(), Some("joint"));
+    assert_eq!(
+        Example::from("SNCF").operation(SoldeType::Credit).montant,
+        0
+    );
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use common::{date, debit, in_account, releve};
+use my_bank_statements::ventilation::compiled::CompiledSpec;
+use my_bank_statements::ventilation::explain::{
+    Decision, OperationQuery, explain, find_operations,
+};
+use my_bank_statements::ventilation::model::{Resolution, VentilationSpec};
+use my_bank_statements::ventilation::overrides::{Override, operation_key};
+
+fn spec(resolution: &str) -> VentilationSpec {
+    serde_yaml::from_str(&format!(
+        "
+name: Test
+resolution: {resolution}
+assignments:
+- name: Courses
+  patterns: [MONOPRIX, CARREFOUR]
+  split:
+  - name: Maison
+    percent: 25
+- name: Gros achats
+  priority: 10
+  patterns:
+  - min_amount: 50000
+"
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_explain() {
+    let compiled = CompiledSpec::new(spec("priority")).unwrap();
+
+    let explanation = explain(
+        &compiled,
+        &debit(date(2024, 1, 5), "CB CARREFOUR CITY", 4000),
+        None,
+        None,
+    );
+    assert_eq!(explanation.assignments.len(), 2);
+    let courses = &explanation.assignments[0].patterns;
+    assert_eq!(courses[0].span, None);
+    assert_eq!(courses[1].span, Some((3, 12)));
+    assert!(courses[1].matched());
+    // The rule of Gros achats has no regex but its amount condition fails
+    let gros_achats = &explanation.assignments[1].patterns[0];
+    assert!(gros_achats.span.is_some());
+    assert!(!gros_achats.conditions);
+    assert!(!gros_achats.matched());
+    match &explanation.decision {
+        Decision::Single(m) => assert_eq!(m.assignment, "Courses"),
+        other => panic!("expected a single match, got {other:?}"),
+    }
+    assert_eq!(
+        explanation.allocation,
+        vec![("Maison".to_string(), 1000), ("Courses".to_string(), 3000)]
+    );
+    let text = explanation.to_string();
+    assert!(text.contains("pattern 2 'CARREFOUR': matches CB [CARREFOUR] CITY"));
+    assert!(
+        text.contains(
+            "pattern 1 '* [montant >= 50000]': description matches, conditions do not hold"
+        )
+    );
+    assert!(text.contains("Split: Maison 10.00, Courses 30.00"));
+
+    // Priority decides between the two assignments
+    let explanation = explain(
+        &compiled,
+        &debit(date(2024, 1, 6), "CARREFOUR", 60000),
+        None,
+        None,
+    );
+    assert_eq!(explanation.matches.len(), 2);
+    match &explanation.decision {
+        Decision::Resolved { resolution, chosen } => {
+            assert_eq!(*resolution, Resolution::Priority);
+            assert_eq!(chosen.assignment, "Gros achats");
+        }
+        other => panic!("expected a resolved match, got {other:?}"),
+    }
+
+    let explanation = explain(
+        &compiled,
+        &debit(date(2024, 1, 7), "FNAC", 2000),
+        None,
+        None,
+    );
+    assert_eq!(explanation.decision, Decision::NotAssigned);
+    assert!(explanation.allocation.is_empty());
+    assert!(explanation.to_string().contains("not assigned"));
+}
+
+#[test]
+fn test_explain_strict_and_override() {
+    let op = debit(date(2024, 1, 6), "CARREFOUR", 60000);
+    let compiled = CompiledSpec::new(spec("strict")).unwrap();
+    assert_eq!(
+        explain(&compiled, &op, None, None).decision,
+        Decision::Ambiguous
+    );
+
+    let mut spec = spec("strict");
+    spec.overrides.push(Override::new(&op, "Cadeaux"));
+    let compiled = CompiledSpec::new(spec).unwrap();
+    let explanation = explain(&compiled, &op, None, None);
+    assert_eq!(
+        explanation.decision,
+        Decision::Override {
+            id: operation_key(&op),
+            assignment: "Cadeaux".to_string()
+        }
+    );
+    // The patterns are still shown
+    assert_eq!(explanation.matches.len(), 2);
+    assert_eq!(explanation.allocation, vec![("Cadeaux".to_string(), 60000)]);
+}
+
+#[test]
+fn test_find_operations() {
+    let mut operations = vec![
+        debit(date(2024, 1, 5), "CB CARREFOUR CITY", 4000),
+        debit(date(2024, 1, 5), "MONOPRIX", 1500),
+        debit(date(2024, 1, 9), "CB CARREFOUR MARKET", 4000),
+    ];
+    operations[1].id = Some("0123456789abcdef".to_string());
+    let releves = vec![in_account(
+        "courant",
+        releve(date(2024, 1, 31), 100000, operations),
+    )];
+    let descriptions = |query: OperationQuery| {
+        find_operations(&releves, &query)
+            .into_iter()
+            .map(|found| {
+                assert_eq!(found.account, Some("courant"));
+                found.operation.nature_des_operations.clone()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        descriptions(OperationQuery::Id("0123456789abcdef".to_string())),
+        vec!["MONOPRIX"]
+    );
+    let key = operation_key(&releves[0].operations[2]);
+    assert_eq!(
+        descriptions(OperationQuery::Id(key)),
+        vec!["CB CARREFOUR MARKET"]
+    );
+    assert_eq!(
+        descriptions(OperationQuery::DateAmount {
+            date: date(2024, 1, 5),
+            montant: 4000
+        }),
+        vec!["CB CARREFOUR CITY"]
+    );
+    // Signed as shown by the explanation, a debit is negative
+    assert_eq!(
+        descriptions(OperationQuery::DateAmount {
+            date: date(2024, 1, 5),
+            montant: -4000
+        }),
+        vec!["CB CARREFOUR CITY"]
+    );
+    assert!(
+        descriptions(OperationQuery::DateAmount {
+            date: date(2024, 1, 5),
+            montant: -400
+        })
+        .is_empty()
+    );
+    assert_eq!(
+        descriptions(OperationQuery::Description("carrefour".to_string())),
+        vec!["CB CARREFOUR CITY", "CB CARREFOUR MARKET"]
+    );
+}
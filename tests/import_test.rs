@@ -0,0 +1,205 @@
+use chrono::NaiveDate;
+use my_bank_statements::SoldeType;
+use my_bank_statements::import::{
+    CsvColumn, CsvFormat, ImportError, QifFormat, parse_csv, parse_ofx, parse_qif,
+};
+use my_bank_statements::parser::OperationKind;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+#[test]
+fn test_csv_default_format() {
+    let text = "\
+Compte de chèques ****1234;Solde au 15/01/2024;1 856,40
+Date operation;Libelle court;Type operation;Libelle operation;Montant operation
+15/01/2024;FACTURE CARTE;Carte;FACTURE CARTE DU 130124 CARREFOUR CITY CARTE 4974XXXXXXXX1234;-45,60
+12/01/2024;VIREMENT RECU;Virement;VIR SEPA RECU /DE ACME SAS /MOTIF SALAIRE;2 500,00
+";
+    let releve = parse_csv(text, &CsvFormat::default()).unwrap();
+
+    assert_eq!(releve.date_du_releve, date(2024, 1, 15));
+    assert_eq!(releve.operations.len(), 2);
+    // Sorted by date
+    assert_eq!(releve.operations[0].date, date(2024, 1, 12));
+    assert!(matches!(
+        releve.operations[0].montant_type,
+        SoldeType::Credit
+    ));
+    assert_eq!(releve.operations[0].montant, 250000);
+    assert!(matches!(
+        releve.operations[1].montant_type,
+        SoldeType::Debit
+    ));
+    assert_eq!(releve.operations[1].montant, 4560);
+    assert_eq!(
+        releve.operations[1].details.as_ref().unwrap().kind,
+        OperationKind::CarteBancaire
+    );
+    assert_eq!(releve.total_des_operations_debit, 4560);
+    assert_eq!(releve.total_des_operations_credit, 250000);
+    assert_eq!(releve.check_debit, releve.total_des_operations_debit);
+}
+
+#[test]
+fn test_csv_named_columns_and_balance() {
+    let text = "\
+date,value date,label,debit,credit,balance
+2024-01-02,2024-01-03,PRLV SEPA EDF,\"1,234.56\",,8765.44
+2024-01-05,2024-01-05,VIR RECU ACME,,100.00,8865.44
+";
+    let format = CsvFormat {
+        delimiter: ',',
+        skip_lines: 0,
+        date: CsvColumn::Name("date".to_string()),
+        valeur: Some(CsvColumn::Name("value date".to_string())),
+        description: vec![CsvColumn::Name("label".to_string())],
+        amount: None,
+        debit: Some(CsvColumn::Name("debit".to_string())),
+        credit: Some(CsvColumn::Name("credit".to_string())),
+        balance: Some(CsvColumn::Name("balance".to_string())),
+        date_format: "%Y-%m-%d".to_string(),
+        decimal_comma: false,
+        ..CsvFormat::default()
+    };
+    let releve = parse_csv(text, &format).unwrap();
+
+    assert_eq!(releve.operations[0].valeur, date(2024, 1, 3));
+    assert_eq!(releve.operations[0].montant, 123456);
+    assert!(matches!(
+        releve.operations[0].montant_type,
+        SoldeType::Debit
+    ));
+    assert_eq!(releve.solde_cloture.montant, 886544);
+    // Opening balance derived from the closing one
+    assert_eq!(releve.solde_ouverture.montant, 1000000);
+    assert!(matches!(
+        releve.solde_ouverture.solde_type,
+        SoldeType::Credit
+    ));
+}
+
+#[test]
+fn test_csv_bad_amount_reports_line() {
+    let text = "\
+Compte de chèques ****1234
+Date operation;Libelle court;Type operation;Libelle operation;Montant operation
+15/01/2024;FACTURE CARTE;Carte;CARREFOUR;-45,60
+16/01/2024;FACTURE CARTE;Carte;MONOPRIX;abc
+";
+    let err = parse_csv(text, &CsvFormat::default()).unwrap_err();
+
+    assert!(matches!(err, ImportError::Format { line: 4, .. }));
+}
+
+#[test]
+fn test_csv_non_ascii_delimiter() {
+    let format = CsvFormat {
+        delimiter: '§',
+        ..CsvFormat::default()
+    };
+    let err = parse_csv("Date§Montant\n15/01/2024§-45,60\n", &format).unwrap_err();
+
+    assert!(matches!(err, ImportError::Delimiter('§')));
+}
+
+#[test]
+fn test_ofx_sgml() {
+    let text = "\
+OFXHEADER:100
+DATA:OFXSGML
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20240110
+<TRNAMT>-12.50
+<FITID>0001
+<NAME>PRLV SEPA FREE MOBILE
+<MEMO>FACTURE 0124
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20240125120000[+1:CET]
+<TRNAMT>1500,00
+<NAME>VIR SEPA RECU ACME
+</STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL>
+<BALAMT>3487.50
+<DTASOF>20240131
+</LEDGERBAL>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+    let releve = parse_ofx(text).unwrap();
+
+    assert_eq!(releve.date_du_releve, date(2024, 1, 31));
+    assert_eq!(releve.operations.len(), 2);
+    assert_eq!(
+        releve.operations[0].nature_des_operations,
+        "PRLV SEPA FREE MOBILE FACTURE 0124"
+    );
+    assert_eq!(releve.operations[0].montant, 1250);
+    assert_eq!(releve.operations[1].date, date(2024, 1, 25));
+    assert_eq!(releve.operations[1].montant, 150000);
+    assert_eq!(releve.solde_cloture.montant, 348750);
+    assert_eq!(releve.solde_ouverture.montant, 200000);
+}
+
+#[test]
+fn test_qif() {
+    let text = "\
+!Type:Bank
+D31/01/2024
+T-1 234,56
+PLOYER
+MJANVIER
+^
+D05/01/2024
+T50,00
+PREMBOURSEMENT
+";
+    let format = QifFormat {
+        decimal_comma: true,
+        ..QifFormat::default()
+    };
+    // The last record is read without its closing ^
+    let releve = parse_qif(text, &format).unwrap();
+
+    assert_eq!(releve.date_du_releve, date(2024, 1, 31));
+    assert_eq!(releve.operations[0].nature_des_operations, "REMBOURSEMENT");
+    assert_eq!(releve.operations[1].nature_des_operations, "LOYER JANVIER");
+    assert_eq!(releve.operations[1].montant, 123456);
+    assert_eq!(releve.total_des_operations_debit, 123456);
+    assert_eq!(releve.total_des_operations_credit, 5000);
+    // No balance in QIF: soldes relative to 0
+    assert_eq!(releve.solde_ouverture.montant, 0);
+    assert!(matches!(releve.solde_cloture.solde_type, SoldeType::Debit));
+    assert_eq!(releve.solde_cloture.montant, 118456);
+}
+
+#[test]
+fn test_qif_several_accounts() {
+    let text = "\
+!Option:AutoSwitch
+!Account
+NCourant
+TBank
+^
+!Type:Bank
+D31/01/2024
+T-12,00
+PLOYER
+^
+";
+    let format = QifFormat {
+        decimal_comma: true,
+        ..QifFormat::default()
+    };
+    let err = parse_qif(text, &format).unwrap_err();
+
+    assert!(matches!(err, ImportError::Format { line: 2, .. }));
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use common::{credit, date, debit};
+use my_bank_statements::export::{JournalFormat, JournalOptions, to_journal};
+use my_bank_statements::parser::model::{Releve, Solde, SoldeType};
+use my_bank_statements::ventilation::model::{Assignment, Share, VentilationSpec};
+use my_bank_statements::ventilation::ventilate::VentilateError;
+
+fn spec() -> VentilationSpec {
+    VentilationSpec {
+        name: "Test".to_string(),
+        assignments: vec![Assignment {
+            name: "Maison/Courses alimentaires".to_string(),
+            patterns: vec!["CARREFOUR".into()],
+            ..Default::default()
+        }],
+        income: vec![Assignment {
+            name: "Salaire".to_string(),
+            patterns: vec!["ACME".into()],
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+fn releves() -> Vec<Releve> {
+    vec![
+        Releve {
+            date_du_releve: date(2024, 1, 13),
+            solde_ouverture: Solde {
+                solde_type: SoldeType::Credit,
+                montant: 100000,
+            },
+            solde_cloture: Solde {
+                solde_type: SoldeType::Credit,
+                montant: 295000,
+            },
+            total_des_operations_debit: 5000,
+            total_des_operations_credit: 200000,
+            check_debit: 5000,
+            check_credit: 200000,
+            operations: vec![
+                debit(date(2024, 1, 5), "CARREFOUR CITY", 5000),
+                credit(date(2024, 1, 10), "VIR SEPA RECU ACME", 200000),
+            ],
+            account: None,
+        },
+        Releve {
+            date_du_releve: date(2024, 2, 13),
+            solde_ouverture: Solde {
+                solde_type: SoldeType::Credit,
+                montant: 295000,
+            },
+            solde_cloture: Solde {
+                solde_type: SoldeType::Credit,
+                montant: 294000,
+            },
+            total_des_operations_debit: 1000,
+            total_des_operations_credit: 0,
+            check_debit: 1000,
+            check_credit: 0,
+            operations: vec![debit(date(2024, 2, 1), "COMMISSIONS", 1000)],
+            account: None,
+        },
+    ]
+}
+
+#[test]
+fn test_ledger_journal() {
+    let options = JournalOptions {
+        bank_account: "Assets:BNP:Cheques".to_string(),
+        ..JournalOptions::default()
+    };
+    let journal = to_journal(&releves(), &spec(), JournalFormat::Ledger, &options).unwrap();
+
+    assert_eq!(
+        journal,
+        "\
+2024-01-05 * Solde ouverture
+    Assets:BNP:Cheques  1000.00 EUR
+    Equity:Opening-Balances  -1000.00 EUR
+
+2024-01-05 * CARREFOUR CITY
+    Expenses:Maison:Courses alimentaires  50.00 EUR
+    Assets:BNP:Cheques  -50.00 EUR
+
+2024-01-10 * VIR SEPA RECU ACME
+    Income:Salaire  -2000.00 EUR
+    Assets:BNP:Cheques  2000.00 EUR
+
+2024-01-13 * Solde cloture
+    Assets:BNP:Cheques  0.00 EUR = 2950.00 EUR
+
+2024-02-01 * Solde ouverture
+    Assets:BNP:Cheques  0.00 EUR = 2950.00 EUR
+
+2024-02-01 * COMMISSIONS
+    Expenses:Unassigned  10.00 EUR
+    Assets:BNP:Cheques  -10.00 EUR
+
+2024-02-13 * Solde cloture
+    Assets:BNP:Cheques  0.00 EUR = 2940.00 EUR
+
+"
+    );
+}
+
+#[test]
+fn test_beancount_journal() {
+    let mut options = JournalOptions {
+        suspense_account: "Expenses:Suspense".to_string(),
+        ..JournalOptions::default()
+    };
+    options
+        .accounts
+        .insert("Salaire".to_string(), "Income:Work:Acme".to_string());
+    let journal = to_journal(&releves(), &spec(), JournalFormat::Beancount, &options).unwrap();
+
+    assert!(journal.starts_with(
+        "\
+2024-01-05 open Assets:Bank
+2024-01-05 open Equity:Opening-Balances
+2024-01-05 open Expenses:Maison:Courses-alimentaires
+2024-01-05 open Expenses:Suspense
+2024-01-05 open Income:Work:Acme
+"
+    ));
+    assert!(journal.contains(
+        "2024-01-05 * \"CARREFOUR CITY\"\n  Expenses:Maison:Courses-alimentaires  50.00 EUR\n  Assets:Bank  -50.00 EUR\n"
+    ));
+    // Balances are checked at the beginning of the day
+    assert!(journal.contains("2024-01-14 balance Assets:Bank  2950.00 EUR\n"));
+    assert!(journal.contains("2024-02-01 balance Assets:Bank  2950.00 EUR\n"));
+    assert!(journal.contains("2024-02-14 balance Assets:Bank  2940.00 EUR\n"));
+}
+
+#[test]
+fn test_journal_multiple_match() {
+    let mut spec = spec();
+    spec.assignments.push(Assignment {
+        name: "Sorties".to_string(),
+        patterns: vec!["CITY".into()],
+        ..Default::default()
+    });
+
+    assert!(matches!(
+        to_journal(
+            &releves(),
+            &spec,
+            JournalFormat::Hledger,
+            &JournalOptions::default()
+        ),
+        Err(VentilateError::MultipleMatch { .. })
+    ));
+}
+
+#[test]
+fn test_journal_split() {
+    let mut spec = spec();
+    spec.assignments[0].split.push(Share {
+        name: "Maison/Entretien".to_string(),
+        percent: Some(30.0),
+        amount: None,
+    });
+    let journal = to_journal(
+        &releves(),
+        &spec,
+        JournalFormat::Ledger,
+        &JournalOptions::default(),
+    )
+    .unwrap();
+
+    assert!(journal.contains(
+        "\
+2024-01-05 * CARREFOUR CITY
+    Expenses:Maison:Entretien  15.00 EUR
+    Expenses:Maison:Courses alimentaires  35.00 EUR
+    Assets:Bank  -50.00 EUR
+"
+    ));
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::{date, debit, releve};
+use my_bank_statements::ventilation::compiled::CompiledSpec;
+use my_bank_statements::ventilation::lint::{AssignmentCoverage, lint};
+use my_bank_statements::ventilation::model::VentilationSpec;
+use my_bank_statements::ventilation::overrides::Override;
+
+#[test]
+fn test_lint() {
+    let spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+resolution: first_in_file
+assignments:
+- name: Courses
+  patterns: [CARREFOUR, CARREFOUR CITY, MONOPRIX, LECLERC]
+- name: Achats
+  patterns: [AMAZON, CITY]
+",
+    )
+    .unwrap();
+    let releves = vec![releve(
+        date(2024, 1, 31),
+        100000,
+        vec![
+            debit(date(2024, 1, 3), "CARREFOUR CITY", 2000),
+            debit(date(2024, 1, 5), "CARREFOUR MARKET", 3000),
+            debit(date(2024, 1, 8), "MONOPRIX", 1000),
+            debit(date(2024, 1, 9), "AMAZON", 4000),
+            debit(date(2024, 1, 12), "FNAC", 10000),
+        ],
+    )];
+
+    let report = lint(&CompiledSpec::new(spec).unwrap(), &releves);
+
+    let dead: Vec<&str> = report
+        .dead_patterns
+        .iter()
+        .map(|p| p.pattern.as_str())
+        .collect();
+    assert_eq!(dead, vec!["LECLERC"]);
+
+    assert_eq!(report.subsumed_patterns.len(), 1);
+    let subsumed = &report.subsumed_patterns[0];
+    assert_eq!(subsumed.pattern.pattern, "CARREFOUR CITY");
+    assert_eq!(subsumed.pattern.position, 2);
+    assert_eq!(subsumed.by.pattern, "CARREFOUR");
+    assert_eq!(subsumed.operations, 1);
+
+    // CARREFOUR and CARREFOUR CITY both overlap with CITY of Achats
+    let overlaps: Vec<(&str, &str, usize, i64)> = report
+        .overlaps
+        .iter()
+        .map(|o| {
+            (
+                o.first.pattern.as_str(),
+                o.second.pattern.as_str(),
+                o.operations,
+                o.montant,
+            )
+        })
+        .collect();
+    assert_eq!(
+        overlaps,
+        vec![
+            ("CARREFOUR", "CITY", 1, 2000),
+            ("CARREFOUR CITY", "CITY", 1, 2000)
+        ]
+    );
+
+    assert_eq!(
+        report.debit.assignments,
+        vec![
+            AssignmentCoverage {
+                name: "Courses".to_string(),
+                operations: 3,
+                montant: 6000,
+            },
+            AssignmentCoverage {
+                name: "Achats".to_string(),
+                operations: 2,
+                montant: 6000,
+            },
+        ]
+    );
+    assert_eq!(report.debit.operations, 5);
+    assert_eq!(report.debit.montant, 20000);
+    assert_eq!(report.debit.categorised_operations, 4);
+    assert_eq!(report.debit.categorised_montant, 10000);
+    assert_eq!(report.debit.percent(), 50.0);
+    assert_eq!(report.credit.operations, 0);
+}
+
+#[test]
+fn test_lint_identical_patterns() {
+    let mut spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+assignments:
+- name: Courses
+  patterns: [CARREFOUR, CARREF]
+",
+    )
+    .unwrap();
+    let mut override_op = debit(date(2024, 1, 6), "CHEQUE 123", 3000);
+    override_op.id = Some("cheque-123".to_string());
+    spec.overrides.push(Override::new(&override_op, "Courses"));
+    let releves = vec![releve(
+        date(2024, 1, 31),
+        100000,
+        vec![debit(date(2024, 1, 3), "CARREFOUR", 1000), override_op],
+    )];
+
+    let report = lint(&CompiledSpec::new(spec).unwrap(), &releves);
+
+    // Of two patterns matching the same operations, the later one is reported
+    assert_eq!(report.subsumed_patterns.len(), 1);
+    assert_eq!(report.subsumed_patterns[0].pattern.pattern, "CARREF");
+    assert_eq!(report.subsumed_patterns[0].by.pattern, "CARREFOUR");
+    // Overridden operations are categorised
+    assert_eq!(report.debit.categorised_montant, 4000);
+    assert_eq!(report.debit.percent(), 100.0);
+}
@@ -0,0 +1,155 @@
+use my_bank_statements::{ParseError, SoldeType, TextLine, parse_lines};
+
+const CHAR_WIDTH: f64 = 5.0;
+
+/// Build a line from (x, text) segments, in the order the PDF emits them.
+fn line(segments: &[(f64, &str)]) -> TextLine {
+    let mut line = TextLine {
+        page: 1,
+        ..Default::default()
+    };
+    for (x, text) in segments {
+        for (i, c) in text.chars().enumerate() {
+            line.text.push(c);
+            line.xs.push(Some(x + i as f64 * CHAR_WIDTH));
+        }
+    }
+    line
+}
+
+fn header() -> TextLine {
+    line(&[
+        (20.0, "D ate "),
+        (80.0, "Nature des opérations "),
+        (380.0, "Valeur "),
+        (430.0, "Débit "),
+        (500.0, "Crédit"),
+    ])
+}
+
+#[test]
+fn test_credit_detected_from_column_position() {
+    let lines = vec![
+        line(&[(20.0, "RELEVE DE COMPTE CHEQUES")]),
+        line(&[(20.0, "du 14 décembre 2023 au 13 janvier 2024")]),
+        header(),
+        line(&[(80.0, "SOLDE CREDITEUR AU 13.12.2023 1 000,00")]),
+        line(&[
+            (20.0, "05.01 "),
+            (380.0, "05.01 "),
+            (440.0, "50,00"),
+            (80.0, "FACTURE CARTE DU 040124 CIRQUE"),
+        ]),
+        // A credit whose label is not in the keyword list
+        line(&[(20.0, "10.01 "), (380.0, "10.01 "), (505.0, "2 000,00")]),
+        line(&[(80.0, "VIR NOUVEL EMPLOYEUR SALAIRE")]),
+        line(&[(80.0, "TOTAL DES OPERATIONS 50,00 2 000,00")]),
+        line(&[(80.0, "SOLDE CREDITEUR AU 13.01.2024 2 950,00")]),
+    ];
+
+    let releve = parse_lines(&lines).unwrap();
+
+    assert_eq!(releve.operations.len(), 2);
+    assert!(matches!(
+        releve.operations[0].montant_type,
+        SoldeType::Debit
+    ));
+    assert_eq!(releve.operations[0].montant, 5000);
+    assert!(matches!(
+        releve.operations[1].montant_type,
+        SoldeType::Credit
+    ));
+    assert_eq!(
+        releve.operations[1].nature_des_operations,
+        "VIR NOUVEL EMPLOYEUR SALAIRE"
+    );
+    assert_eq!(releve.check_credit, 200000);
+    assert_eq!(releve.check_debit, 5000);
+}
+
+#[test]
+fn test_keyword_fallback_without_positions() {
+    let lines: Vec<TextLine> = [
+        "du 14 décembre 2023 au 13 janvier 2024",
+        "SOLDE CREDITEUR AU 13.12.2023 1 000,00",
+        "05.01 05.01 50,00FACTURE CARTE DU 040124 CIRQUE",
+        "10.01 10.01 2 000,00VIR SEPA RECU /DE EMPLOYEUR",
+        "TOTAL DES OPERATIONS 50,00 2 000,00",
+        "SOLDE CREDITEUR AU 13.01.2024 2 950,00",
+    ]
+    .iter()
+    .map(|l| TextLine::plain(1, l))
+    .collect();
+
+    let releve = parse_lines(&lines).unwrap();
+
+    assert!(matches!(
+        releve.operations[0].montant_type,
+        SoldeType::Debit
+    ));
+    assert!(matches!(
+        releve.operations[1].montant_type,
+        SoldeType::Credit
+    ));
+}
+
+#[test]
+fn test_totals_mismatch_reports_page_and_line() {
+    let lines = vec![
+        TextLine::plain(1, "du 14 décembre 2023 au 13 janvier 2024"),
+        TextLine::plain(1, "SOLDE CREDITEUR AU 13.12.2023 1 000,00"),
+        TextLine::plain(1, "05.01 05.01 50,00FACTURE CARTE DU 040124 CIRQUE"),
+        TextLine::plain(2, "TOTAL DES OPERATIONS 80,00 0,00"),
+        TextLine::plain(2, "SOLDE CREDITEUR AU 13.01.2024 920,00"),
+    ];
+
+    match parse_lines(&lines).unwrap_err() {
+        ParseError::TotalsMismatch {
+            provenance,
+            montant_type,
+            total,
+            check,
+        } => {
+            assert!(matches!(montant_type, SoldeType::Debit));
+            assert_eq!(total, 8000);
+            assert_eq!(check, 5000);
+            assert_eq!(provenance.page, Some(2));
+            assert_eq!(
+                provenance.line.as_deref(),
+                Some("TOTAL DES OPERATIONS 80,00 0,00")
+            );
+        }
+        e => panic!("Expected TotalsMismatch error, got {e:?}"),
+    }
+}
+
+#[test]
+fn test_missing_header() {
+    let lines = vec![TextLine::plain(1, "SOLDE CREDITEUR AU 13.12.2023 1 000,00")];
+
+    let err = parse_lines(&lines).unwrap_err();
+    assert!(matches!(err, ParseError::MissingHeader { .. }));
+    assert!(format!("{err}").contains("Could not find date pattern"));
+}
+
+#[test]
+fn test_bad_operation_date() {
+    let lines = vec![
+        TextLine::plain(1, "du 14 décembre 2023 au 13 janvier 2024"),
+        TextLine::plain(1, "SOLDE CREDITEUR AU 13.12.2023 1 000,00"),
+        TextLine::plain(3, "35.01 05.01 50,00FACTURE CARTE DU 040124 CIRQUE"),
+        TextLine::plain(3, "TOTAL DES OPERATIONS 50,00 0,00"),
+        TextLine::plain(3, "SOLDE CREDITEUR AU 13.01.2024 950,00"),
+    ];
+
+    let err = parse_lines(&lines).unwrap_err().with_path("releve.pdf");
+    match &err {
+        ParseError::BadDate { provenance, value } => {
+            assert_eq!(value, "35.01");
+            assert_eq!(provenance.page, Some(3));
+            assert_eq!(provenance.path.as_deref(), Some("releve.pdf"));
+        }
+        e => panic!("Expected BadDate error, got {e:?}"),
+    }
+    assert!(format!("{err}").contains("releve.pdf, page 3"));
+}
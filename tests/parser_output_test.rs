@@ -0,0 +1,26 @@
+use my_bank_statements::Releve;
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_single_releve_written_as_list() {
+    let dir = std::env::temp_dir().join(format!("parser_output_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("export.qif");
+    let output = dir.join("releves.yml");
+    fs::write(&input, "!Type:Bank\nD31/01/2024\nT-12.34\nPLOYER\n^\n").unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_my-bank-statements-parser"))
+        .arg(&input)
+        .arg(&output)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // The other tools read a list of releves, even of one
+    let content = fs::read_to_string(&output).unwrap();
+    let releves: Vec<Releve> = serde_yaml::from_str(&content).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(releves.len(), 1);
+    assert_eq!(releves[0].operations[0].montant, 1234);
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{date, debit, releve};
+use my_bank_statements::parser::model::Operation;
+use my_bank_statements::ventilation::model::{Assignment, VentilationSpec};
+use my_bank_statements::ventilation::period::{DateField, Period, ventilate_by_period};
+use my_bank_statements::ventilation::ventilate::ventilate;
+use std::collections::BTreeMap;
+
+fn spec() -> VentilationSpec {
+    VentilationSpec {
+        name: "Test".to_string(),
+        assignments: vec![Assignment {
+            name: "Courses".to_string(),
+            patterns: vec!["CARREFOUR".into()],
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+fn amounts(entries: &[(&str, i64)]) -> BTreeMap<String, i64> {
+    entries.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+}
+
+#[test]
+fn test_period_labels() {
+    let day = date(2024, 11, 3);
+    assert_eq!(Period::Month.label(day), "2024-11");
+    assert_eq!(Period::Quarter.label(day), "2024-Q4");
+    assert_eq!(Period::Year.label(day), "2024");
+    assert_eq!("quarter".parse::<Period>(), Ok(Period::Quarter));
+    assert!("week".parse::<Period>().is_err());
+}
+
+#[test]
+fn test_ventilate_by_month() {
+    let releves = [releve(
+        date(2024, 4, 13),
+        100000,
+        vec![
+            debit(date(2024, 1, 5), "CARREFOUR", 4000),
+            Operation {
+                valeur: date(2024, 2, 1),
+                ..debit(date(2024, 1, 31), "CARREFOUR", 1000)
+            },
+            debit(date(2024, 3, 2), "CARREFOUR", 2500),
+            debit(date(2024, 3, 8), "PHARMACIE", 700),
+        ],
+    )];
+    let result = ventilate(spec(), &releves).unwrap();
+
+    let by_date = ventilate_by_period(&result, Period::Month, DateField::Date);
+    assert_eq!(by_date.periods, vec!["2024-01", "2024-03"]);
+    assert_eq!(
+        by_date.debit.categories["Courses"],
+        amounts(&[("2024-01", 5000), ("2024-03", 2500)])
+    );
+    assert_eq!(by_date.debit.not_assigned, amounts(&[("2024-03", 700)]));
+    assert_eq!(
+        by_date.debit.totals,
+        amounts(&[("2024-01", 5000), ("2024-03", 3200)])
+    );
+    assert!(by_date.credit.totals.is_empty());
+
+    // The operation of January 31st has a value date in February
+    let by_valeur = ventilate_by_period(&result, Period::Month, DateField::Valeur);
+    assert_eq!(by_valeur.periods, vec!["2024-01", "2024-02", "2024-03"]);
+    assert_eq!(by_valeur.debit.categories["Courses"]["2024-02"], 1000);
+
+    let by_year = ventilate_by_period(&result, Period::Year, DateField::Date);
+    assert_eq!(by_year.debit.totals, amounts(&[("2024", 8200)]));
+}
@@ -0,0 +1,129 @@
+use my_bank_statements::parser::layout::lines_from_text;
+use my_bank_statements::parser::{
+    AccountHolder, BnpParibas, SocieteGenerale, detect_account_holder,
+};
+use my_bank_statements::{ParserProfile, TextLine, parse_lines, parse_lines_with_profile};
+
+fn lines(page_lines: &[(u32, &str)]) -> Vec<TextLine> {
+    page_lines
+        .iter()
+        .map(|(page, text)| TextLine::plain(*page, text))
+        .collect()
+}
+
+fn statement() -> Vec<TextLine> {
+    lines(&[
+        (1, "RELEVE DE COMPTE CHEQUES"),
+        (1, "M JEAN DUPONT"),
+        (1, "BATIMENT B"),
+        (1, "12 RUE DES LILAS"),
+        (1, "75011 PARIS"),
+        (1, "du 14 décembre 2023 au 13 janvier 2024"),
+        (1, "SOLDE CREDITEUR AU 13.12.2023 1 000,00"),
+        (1, "05.01 05.01 50,00FACTURE CARTE DU 040124"),
+        (1, "CIRQUE"),
+        (1, "REF 0042"),
+        // The holder block is repeated at the top of the next page
+        (2, "M JEAN DUPONT"),
+        (2, "BATIMENT B"),
+        (2, "12 RUE DES LILAS"),
+        (2, "75011 PARIS"),
+        (2, "12.01 12.01 30,00PRLV SEPA EDF"),
+        (2, "TOTAL DES OPERATIONS 80,00 0,00"),
+        (2, "SOLDE CREDITEUR AU 13.01.2024 920,00"),
+    ])
+}
+
+#[test]
+fn test_detect_account_holder() {
+    assert_eq!(
+        detect_account_holder(&statement(), &BnpParibas),
+        Some(AccountHolder {
+            name: "M JEAN DUPONT".to_string(),
+            address: vec![
+                "BATIMENT B".to_string(),
+                "12 RUE DES LILAS".to_string(),
+                "75011 PARIS".to_string(),
+            ],
+        })
+    );
+}
+
+#[test]
+fn test_detect_account_holder_societe_generale() {
+    let sg = lines_from_text(include_str!("fixtures/societe_generale.txt"));
+
+    assert_eq!(
+        detect_account_holder(&sg, &SocieteGenerale).unwrap().name,
+        "M JEAN DUPONT"
+    );
+
+    // A name in the description of an operation is not in the header
+    let sg = lines_from_text(
+        "\
+RELEVÉ DES OPÉRATIONS
+du 01/01/2024 au 31/01/2024
+25/01/2024 25/01/2024 VIR RECU 1234567890 2 500,00
+MME CLAIRE MARTIN
+",
+    );
+
+    assert_eq!(detect_account_holder(&sg, &SocieteGenerale), None);
+}
+
+#[test]
+fn test_account_holder_lines_are_not_in_description() {
+    let releve = parse_lines(&statement()).unwrap();
+
+    assert_eq!(
+        releve.operations[0].nature_des_operations,
+        "FACTURE CARTE DU 040124 CIRQUE REF 0042"
+    );
+    assert_eq!(releve.operations[1].nature_des_operations, "PRLV SEPA EDF");
+}
+
+#[test]
+fn test_profile_stop_lines() {
+    let profile: ParserProfile = serde_yaml::from_str(
+        r#"
+stop_starts_with:
+- "REF "
+"#,
+    )
+    .unwrap();
+
+    let releve = parse_lines_with_profile(&statement(), &profile).unwrap();
+
+    assert_eq!(
+        releve.operations[0].nature_des_operations,
+        "FACTURE CARTE DU 040124 CIRQUE"
+    );
+}
+
+#[test]
+fn test_profile_without_detection() {
+    let profile = ParserProfile {
+        detect_account_holder: false,
+        ..Default::default()
+    };
+
+    let releve = parse_lines_with_profile(&statement(), &profile).unwrap();
+
+    assert_eq!(
+        releve.operations[0].nature_des_operations,
+        "FACTURE CARTE DU 040124 CIRQUE REF 0042 M JEAN DUPONT BATIMENT B 12 RUE DES LILAS 75011 PARIS"
+    );
+
+    let profile = ParserProfile {
+        stop_contains: vec!["DUPONT".to_string()],
+        detect_account_holder: false,
+        ..Default::default()
+    };
+
+    let releve = parse_lines_with_profile(&statement(), &profile).unwrap();
+
+    assert_eq!(
+        releve.operations[0].nature_des_operations,
+        "FACTURE CARTE DU 040124 CIRQUE REF 0042"
+    );
+}
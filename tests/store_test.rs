@@ -0,0 +1,237 @@
+mod common;
+
+use common::{date, debit, releve};
+use my_bank_statements::store::{ImportOutcome, Store, content_hash};
+use my_bank_statements::ventilation::model::VentilationSpec;
+use my_bank_statements::ventilation::ventilate::ventilate;
+
+#[test]
+fn test_import_is_idempotent() {
+    let mut store = Store::open_in_memory().unwrap();
+    // Two identical operations on the same day are both kept
+    let january = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![
+            debit(date(2024, 1, 5), "CAFE DE LA GARE", 250),
+            debit(date(2024, 1, 5), "CAFE DE LA GARE", 250),
+            debit(date(2024, 1, 8), "CARREFOUR", 4500),
+        ],
+    );
+
+    assert_eq!(
+        store.import_releve("bnp", &january, "2024-01.pdf").unwrap(),
+        ImportOutcome::Inserted {
+            operations: 3,
+            skipped: 0
+        }
+    );
+    assert_eq!(
+        store.import_releve("bnp", &january, "2024-01.pdf").unwrap(),
+        ImportOutcome::Unchanged
+    );
+    // The same statement in another account is another releve
+    assert!(matches!(
+        store
+            .import_releve("sg", &january, "sg-2024-01.pdf")
+            .unwrap(),
+        ImportOutcome::Inserted { operations: 3, .. }
+    ));
+
+    let releves = store.releves(Some("bnp")).unwrap();
+    assert_eq!(releves.len(), 1);
+    assert_eq!(releves[0].operations.len(), 3);
+    assert_eq!(releves[0].check_debit, 5000);
+    assert_eq!(content_hash(&releves[0]), content_hash(&january));
+
+    let log = store.import_log().unwrap();
+    assert_eq!(log.len(), 3);
+    assert_eq!(log[1].outcome, "unchanged");
+    assert_eq!(log[2].account, "sg");
+}
+
+#[test]
+fn test_shared_operations_are_not_counted_twice() {
+    let mut store = Store::open_in_memory().unwrap();
+    store
+        .import_releve(
+            "bnp",
+            &releve(
+                date(2024, 1, 13),
+                100000,
+                vec![debit(date(2024, 1, 20), "LOYER", 80000)],
+            ),
+            "export.csv",
+        )
+        .unwrap();
+
+    let february = releve(
+        date(2024, 2, 13),
+        100000,
+        vec![
+            debit(date(2024, 1, 20), "LOYER", 80000),
+            debit(date(2024, 2, 3), "EDF", 6000),
+        ],
+    );
+    assert_eq!(
+        store
+            .import_releve("bnp", &february, "2024-02.pdf")
+            .unwrap(),
+        ImportOutcome::Inserted {
+            operations: 1,
+            skipped: 1
+        }
+    );
+
+    let operations = store.operations(Some("bnp"), None, None).unwrap();
+    assert_eq!(operations.len(), 2);
+    let february_operations = store
+        .operations(Some("bnp"), Some(date(2024, 2, 1)), Some(date(2024, 2, 28)))
+        .unwrap();
+    assert_eq!(february_operations.len(), 1);
+    assert_eq!(february_operations[0].nature_des_operations, "EDF");
+}
+
+#[test]
+fn test_conflict_and_remove() {
+    let mut store = Store::open_in_memory().unwrap();
+    let first = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![debit(date(2024, 1, 5), "CARREFOUR", 4500)],
+    );
+    let fixed = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![debit(date(2024, 1, 5), "CARREFOUR CITY", 4500)],
+    );
+
+    store.import_releve("bnp", &first, "a.pdf").unwrap();
+    assert_eq!(
+        store.import_releve("bnp", &fixed, "a.pdf").unwrap(),
+        ImportOutcome::Conflict {
+            existing_hash: content_hash(&first)
+        }
+    );
+
+    assert!(store.remove_releve("bnp", date(2024, 1, 13)).unwrap());
+    assert!(!store.remove_releve("bnp", date(2024, 1, 13)).unwrap());
+    assert!(matches!(
+        store.import_releve("bnp", &fixed, "a.pdf").unwrap(),
+        ImportOutcome::Inserted { operations: 1, .. }
+    ));
+    assert_eq!(
+        store.releves(None).unwrap()[0].operations[0].nature_des_operations,
+        "CARREFOUR CITY"
+    );
+}
+
+#[test]
+fn test_remove_overlapping_releve() {
+    let mut store = Store::open_in_memory().unwrap();
+    let loyer = debit(date(2024, 1, 20), "LOYER", 80000);
+    let january = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![debit(date(2024, 1, 8), "CARREFOUR", 4500), loyer.clone()],
+    );
+    let february = releve(
+        date(2024, 2, 13),
+        100000,
+        vec![loyer, debit(date(2024, 2, 3), "EDF", 6000)],
+    );
+    store.import_releve("bnp", &january, "export.csv").unwrap();
+    store
+        .import_releve("bnp", &february, "2024-02.pdf")
+        .unwrap();
+
+    // The rent, stored with january, is in february too: it is kept
+    assert!(store.remove_releve("bnp", date(2024, 1, 13)).unwrap());
+    let descriptions = |store: &Store| {
+        store
+            .operations(Some("bnp"), None, None)
+            .unwrap()
+            .into_iter()
+            .map(|op| op.nature_des_operations)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(descriptions(&store), vec!["LOYER", "EDF"]);
+    assert_eq!(store.releves(Some("bnp")).unwrap()[0].operations.len(), 2);
+
+    // Importing january again stores only its own operation
+    assert!(matches!(
+        store.import_releve("bnp", &january, "export.csv").unwrap(),
+        ImportOutcome::Inserted {
+            operations: 1,
+            skipped: 1
+        }
+    ));
+    assert!(store.remove_releve("bnp", date(2024, 2, 13)).unwrap());
+    assert_eq!(descriptions(&store), vec!["CARREFOUR", "LOYER"]);
+}
+
+#[test]
+fn test_overlapping_releves_read_back() {
+    let mut store = Store::open_in_memory().unwrap();
+    let loyer = debit(date(2024, 1, 20), "LOYER", 80000);
+    let january = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![debit(date(2024, 1, 8), "CARREFOUR", 4500), loyer.clone()],
+    );
+    let february = releve(
+        date(2024, 2, 13),
+        15500,
+        vec![loyer, debit(date(2024, 2, 3), "EDF", 6000)],
+    );
+    store.import_releve("bnp", &january, "export.csv").unwrap();
+    store
+        .import_releve("bnp", &february, "2024-02.pdf")
+        .unwrap();
+
+    // The rent is stored once but both releves list it
+    assert_eq!(store.operations(None, None, None).unwrap().len(), 3);
+    let releves = store.releves(Some("bnp")).unwrap();
+    for releve in &releves {
+        assert_eq!(releve.operations.len(), 2);
+        assert_eq!(releve.check_debit, releve.total_des_operations_debit);
+    }
+
+    // Ventilated once
+    let spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+assignments:
+- name: Logement
+  patterns: [LOYER]
+",
+    )
+    .unwrap();
+    let result = ventilate(spec, &releves).unwrap();
+    assert_eq!(result.debit.ventilation.get("Logement"), Some(&80000));
+    assert_eq!(result.debit.not_assigned, 10500);
+    assert_eq!(result.debit.repeated, 80000);
+}
+
+#[test]
+fn test_store_persists_details() {
+    let path = std::env::temp_dir().join(format!("store_test_{}.sqlite", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut january = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![debit(date(2024, 1, 5), "PRLV SEPA EDF", 6000)],
+    );
+    january.operations[0].details = Some(my_bank_statements::parser::decompose("PRLV SEPA EDF"));
+
+    {
+        let mut store = Store::open(path).unwrap();
+        store.import_releve("bnp", &january, "a.pdf").unwrap();
+    }
+    let store = Store::open(path).unwrap();
+    let operations = store.operations(None, None, None).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(store.accounts().unwrap(), vec!["bnp".to_string()]);
+    assert_eq!(operations[0].details, january.operations[0].details);
+}
@@ -0,0 +1,254 @@
+mod common;
+
+use common::{credit, date, debit, in_account, releve};
+use my_bank_statements::export::{JournalFormat, JournalOptions, to_journal};
+use my_bank_statements::parser::model::Releve;
+use my_bank_statements::ventilation::compiled::CompiledSpec;
+use my_bank_statements::ventilation::explain::{Decision, explain};
+use my_bank_statements::ventilation::lint::lint;
+use my_bank_statements::ventilation::model::VentilationSpec;
+use my_bank_statements::ventilation::transfers::{Transfers, find_transfers, transfer_at};
+use my_bank_statements::ventilation::ventilate::ventilate;
+
+fn releves() -> Vec<Releve> {
+    vec![
+        in_account(
+            "courant",
+            releve(
+                date(2024, 1, 31),
+                100000,
+                vec![
+                    debit(date(2024, 1, 3), "CARREFOUR", 4000),
+                    debit(
+                        date(2024, 1, 5),
+                        "VIR CPTE A CPTE EMIS /MOTIF EPARGNE",
+                        50000,
+                    ),
+                    debit(date(2024, 1, 9), "VIR CPTE A CPTE EMIS LEP", 20000),
+                    // Same amount as the transfer, but too late to be its other leg
+                    credit(date(2024, 1, 20), "VIR SEPA RECU /DE ACME", 50000),
+                ],
+            ),
+        ),
+        in_account(
+            "livret",
+            releve(
+                date(2024, 1, 31),
+                100000,
+                vec![credit(
+                    date(2024, 1, 6),
+                    "VIR CPTE A CPTE RECU /MOTIF EPARGNE",
+                    50000,
+                )],
+            ),
+        ),
+    ]
+}
+
+fn spec() -> VentilationSpec {
+    serde_yaml::from_str(
+        "
+name: Test
+assignments:
+- name: Courses
+  patterns: [CARREFOUR]
+income:
+- name: Salaire
+  patterns: [ACME]
+transfers:
+  accounts:
+  - name: courant
+    iban: FR76 3000 4000 0312 3456 7890 143
+  - name: livret
+  - name: lep
+    labels: [LEP]
+",
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_find_transfers() {
+    let releves = releves();
+    let transfers = find_transfers(&releves, &spec().transfers);
+    assert_eq!(transfers.len(), 2);
+
+    // Both accounts are imported: the two legs are paired
+    let paired = &transfers[0];
+    assert_eq!(paired.montant, 50000);
+    assert_eq!(paired.outgoing.as_ref().unwrap().position, (0, 1));
+    assert_eq!(paired.incoming.as_ref().unwrap().position, (1, 0));
+    assert_eq!(paired.from(), Some("courant"));
+    assert_eq!(paired.to(), Some("livret"));
+
+    // The releves of the LEP are not imported, its label is in the description
+    let single = &transfers[1];
+    assert_eq!(single.montant, 20000);
+    assert!(single.incoming.is_none());
+    assert_eq!(single.from(), Some("courant"));
+    assert_eq!(single.to(), Some("lep"));
+
+    // Out of the window, the legs are not paired
+    let mut config = spec().transfers;
+    config.window_days = 0;
+    assert_eq!(find_transfers(&releves, &config).len(), 1);
+    assert!(find_transfers(&releves, &Transfers::default()).is_empty());
+}
+
+#[test]
+fn test_ventilate_without_transfers() {
+    let releves = releves();
+    let result = ventilate(spec(), &releves).unwrap();
+
+    assert_eq!(result.transfers.len(), 2);
+    assert_eq!(result.debit.ventilation.get("Courses"), Some(&4000));
+    assert_eq!(result.debit.not_assigned, 0);
+    assert_eq!(result.debit.transfers, 70000);
+    assert_eq!(result.debit.transfer_operations.len(), 2);
+    assert_eq!(result.credit.ventilation.get("Salaire"), Some(&50000));
+    assert_eq!(result.credit.not_assigned, 0);
+    assert_eq!(result.credit.transfers, 50000);
+
+    // Without own accounts, transfers are operations like the others
+    let mut no_accounts = spec();
+    no_accounts.transfers = Transfers::default();
+    let result = ventilate(no_accounts, &releves).unwrap();
+    assert!(result.transfers.is_empty());
+    assert_eq!(result.debit.not_assigned, 70000);
+    assert_eq!(result.debit.transfers, 0);
+
+    // Transfers are left out of the coverage of the lint
+    let report = lint(&CompiledSpec::new(spec()).unwrap(), &releves);
+    assert_eq!(report.debit.operations, 1);
+    assert_eq!(report.debit.percent(), 100.0);
+}
+
+#[test]
+fn test_same_amount_is_not_a_transfer() {
+    // A card payment and an unrelated refund of the same amount, the same day
+    let releves = vec![
+        in_account(
+            "courant",
+            releve(
+                date(2024, 1, 31),
+                100000,
+                vec![debit(
+                    date(2024, 1, 5),
+                    "FACTURE CARTE DU 040124 FNAC PARIS CARTE 4974XXXXXXXX1234",
+                    5000,
+                )],
+            ),
+        ),
+        in_account(
+            "livret",
+            releve(
+                date(2024, 1, 31),
+                100000,
+                vec![credit(date(2024, 1, 5), "REMBOURSEMENT MUTUELLE", 5000)],
+            ),
+        ),
+    ];
+    assert!(find_transfers(&releves, &spec().transfers).is_empty());
+    let result = ventilate(spec(), &releves).unwrap();
+    assert_eq!(result.debit.transfers, 0);
+    assert_eq!(result.credit.not_assigned, 5000);
+}
+
+#[test]
+fn test_transfer_by_iban() {
+    let releves = vec![in_account(
+        "livret",
+        releve(
+            date(2024, 1, 31),
+            100000,
+            vec![credit(
+                date(2024, 1, 2),
+                "VIR RECU DE FR7630004000031234567890143",
+                1500,
+            )],
+        ),
+    )];
+    let transfers = find_transfers(&releves, &spec().transfers);
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].from(), Some("courant"));
+    assert_eq!(transfers[0].to(), Some("livret"));
+}
+
+#[test]
+fn test_explain_transfer() {
+    let releves = releves();
+    let mut spec = spec();
+    // A pattern matching the transfer does not categorise it
+    spec.assignments[0].patterns.push("EPARGNE".into());
+    let compiled = CompiledSpec::new(spec).unwrap();
+    let transfers = find_transfers(&releves, &compiled.spec().transfers);
+
+    let incoming = &releves[1].operations[0];
+    let transfer = transfer_at(&transfers, (1, 0));
+    let explanation = explain(&compiled, incoming, Some("livret"), transfer);
+    assert_eq!(
+        explanation.decision,
+        Decision::InternalTransfer {
+            from: Some("courant".to_string()),
+            to: Some("livret".to_string())
+        }
+    );
+    assert!(explanation.allocation.is_empty());
+    assert!(
+        explanation
+            .to_string()
+            .contains("Internal transfer from courant to livret")
+    );
+
+    let outgoing = &releves[0].operations[1];
+    assert_eq!(
+        explain(&compiled, outgoing, Some("courant"), None)
+            .allocation
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_journal_transfers() {
+    let releves = releves();
+    let journal = to_journal(
+        &releves,
+        &spec(),
+        JournalFormat::Ledger,
+        &JournalOptions::default(),
+    )
+    .unwrap();
+
+    // Each leg goes to the other own account
+    assert!(journal.contains(
+        "2024-01-05 * VIR CPTE A CPTE EMIS /MOTIF EPARGNE\n    Assets:livret  500.00 EUR\n"
+    ));
+    assert!(journal.contains(
+        "2024-01-06 * VIR CPTE A CPTE RECU /MOTIF EPARGNE\n    Assets:courant  -500.00 EUR\n"
+    ));
+    assert!(
+        journal.contains("2024-01-09 * VIR CPTE A CPTE EMIS LEP\n    Assets:lep  200.00 EUR\n")
+    );
+    assert!(!journal.contains("Expenses:Unassigned"));
+
+    // Each account has its own bank account, opening balance and assertions
+    assert!(journal.contains(
+        "VIR CPTE A CPTE EMIS LEP\n    Assets:lep  200.00 EUR\n    Assets:Bank:courant  -200.00 EUR\n"
+    ));
+    assert!(journal.contains(
+        "RECU /MOTIF EPARGNE\n    Assets:courant  -500.00 EUR\n    Assets:Bank:livret  500.00 EUR\n"
+    ));
+    assert_eq!(journal.matches("* Solde ouverture\n").count(), 2);
+    assert!(journal.contains("* Solde cloture\n    Assets:Bank:livret  0.00 EUR = 1500.00 EUR\n"));
+    assert!(!journal.contains("Assets:Bank  "));
+
+    // Or the one given for it
+    let options = JournalOptions {
+        bank_accounts: [("livret".to_string(), "Assets:Epargne".to_string())].into(),
+        ..JournalOptions::default()
+    };
+    let journal = to_journal(&releves, &spec(), JournalFormat::Ledger, &options).unwrap();
+    assert!(journal.contains("    Assets:Epargne  500.00 EUR\n"));
+    assert!(journal.contains("    Assets:Bank:courant  -500.00 EUR\n"));
+}
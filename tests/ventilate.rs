@@ -1,6 +1,12 @@
-use chrono::NaiveDate;
+mod common;
+
+use common::{credit, date, debit, in_account, releve};
 use my_bank_statements::parser::model::{Operation, Releve, Solde, SoldeType};
-use my_bank_statements::ventilation::model::{Assignment, VentilationSpec};
+use my_bank_statements::ventilation::compiled::CompiledSpec;
+use my_bank_statements::ventilation::model::{
+    Assignment, Order, Pattern, Resolution, VentilationSpec, category_ancestors, truncate_category,
+};
+use my_bank_statements::ventilation::overrides::{duplicate_keys, operation_key};
 use my_bank_statements::ventilation::ventilate::{VentilateError, ventilate};
 
 #[test]
@@ -10,61 +16,33 @@ fn test_ventilate() {
         assignments: vec![
             Assignment {
                 name: "Cirque".to_string(),
-                patterns: vec!["CIRQUE".to_string()],
-                ignore: false,
+                patterns: vec!["CIRQUE".into()],
+                ..Default::default()
             },
             Assignment {
                 name: "Restaurant".to_string(),
-                patterns: vec!["RESTAURANT".to_string(), "REST\\.".to_string()],
-                ignore: false,
+                patterns: vec!["RESTAURANT".into(), "REST\\.".into()],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     };
 
-    let releve = Releve {
-        date_du_releve: NaiveDate::from_ymd_opt(2024, 1, 13).unwrap(),
-        solde_ouverture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 100000,
-        },
-        solde_cloture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 90000,
-        },
-        total_des_operations_debit: 8000,
-        total_des_operations_credit: 200000,
-        check_debit: 8000,
-        check_credit: 200000,
-        operations: vec![
-            Operation {
-                date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-                nature_des_operations: "CIRQUE DU SOLEIL".to_string(),
-                valeur: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-                montant: 5000,
-                montant_type: SoldeType::Debit,
-            },
-            Operation {
-                date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
-                nature_des_operations: "RESTAURANT CHEZ PAUL".to_string(),
-                valeur: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
-                montant: 3000,
-                montant_type: SoldeType::Debit,
-            },
-            Operation {
-                date: NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(),
-                nature_des_operations: "SALAIRE".to_string(),
-                valeur: NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(),
-                montant: 200000,
-                montant_type: SoldeType::Credit,
-            },
+    let releve = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![
+            debit(date(2024, 1, 5), "CIRQUE DU SOLEIL", 5000),
+            debit(date(2024, 1, 10), "RESTAURANT CHEZ PAUL", 3000),
+            credit(date(2024, 1, 12), "SALAIRE", 200000),
         ],
-    };
+    );
 
     let result = ventilate(spec, &[releve]).unwrap();
 
-    assert_eq!(result.ventilation.get("Cirque"), Some(&5000));
-    assert_eq!(result.ventilation.get("Restaurant"), Some(&3000));
-    assert_eq!(result.ventilation.get("Salaire"), None);
+    assert_eq!(result.debit.ventilation.get("Cirque"), Some(&5000));
+    assert_eq!(result.debit.ventilation.get("Restaurant"), Some(&3000));
+    assert_eq!(result.debit.ventilation.get("Salaire"), None);
 }
 
 #[test]
@@ -74,39 +52,23 @@ fn test_ventilate_multiple_match_error() {
         assignments: vec![
             Assignment {
                 name: "Cirque".to_string(),
-                patterns: vec!["CIRQUE".to_string()],
-                ignore: false,
+                patterns: vec!["CIRQUE".into()],
+                ..Default::default()
             },
             Assignment {
                 name: "Soleil".to_string(),
-                patterns: vec!["SOLEIL".to_string()],
-                ignore: false,
+                patterns: vec!["SOLEIL".into()],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     };
 
-    let releve = Releve {
-        date_du_releve: NaiveDate::from_ymd_opt(2024, 1, 13).unwrap(),
-        solde_ouverture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 100000,
-        },
-        solde_cloture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 95000,
-        },
-        total_des_operations_debit: 5000,
-        total_des_operations_credit: 0,
-        check_debit: 5000,
-        check_credit: 0,
-        operations: vec![Operation {
-            date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-            nature_des_operations: "CIRQUE DU SOLEIL".to_string(),
-            valeur: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-            montant: 5000,
-            montant_type: SoldeType::Debit,
-        }],
-    };
+    let releve = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![debit(date(2024, 1, 5), "CIRQUE DU SOLEIL", 5000)],
+    );
 
     let result = ventilate(spec, &[releve]);
     assert!(result.is_err());
@@ -127,44 +89,28 @@ fn test_ventilate_three_matches_error() {
         assignments: vec![
             Assignment {
                 name: "Cirque".to_string(),
-                patterns: vec!["CIRQUE".to_string()],
-                ignore: false,
+                patterns: vec!["CIRQUE".into()],
+                ..Default::default()
             },
             Assignment {
                 name: "Soleil".to_string(),
-                patterns: vec!["SOLEIL".to_string()],
-                ignore: false,
+                patterns: vec!["SOLEIL".into()],
+                ..Default::default()
             },
             Assignment {
                 name: "Du".to_string(),
-                patterns: vec!["DU".to_string()],
-                ignore: false,
+                patterns: vec!["DU".into()],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     };
 
-    let releve = Releve {
-        date_du_releve: NaiveDate::from_ymd_opt(2024, 1, 13).unwrap(),
-        solde_ouverture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 100000,
-        },
-        solde_cloture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 95000,
-        },
-        total_des_operations_debit: 5000,
-        total_des_operations_credit: 0,
-        check_debit: 5000,
-        check_credit: 0,
-        operations: vec![Operation {
-            date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-            nature_des_operations: "CIRQUE DU SOLEIL".to_string(),
-            valeur: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-            montant: 5000,
-            montant_type: SoldeType::Debit,
-        }],
-    };
+    let releve = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![debit(date(2024, 1, 5), "CIRQUE DU SOLEIL", 5000)],
+    );
 
     let result = ventilate(spec, &[releve]);
     assert!(result.is_err());
@@ -183,39 +129,23 @@ fn test_ventilate_error_display() {
         assignments: vec![
             Assignment {
                 name: "A".to_string(),
-                patterns: vec!["TEST".to_string()],
-                ignore: false,
+                patterns: vec!["TEST".into()],
+                ..Default::default()
             },
             Assignment {
                 name: "B".to_string(),
-                patterns: vec!["TEST".to_string()],
-                ignore: false,
+                patterns: vec!["TEST".into()],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     };
 
-    let releve = Releve {
-        date_du_releve: NaiveDate::from_ymd_opt(2024, 1, 13).unwrap(),
-        solde_ouverture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 100000,
-        },
-        solde_cloture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 95000,
-        },
-        total_des_operations_debit: 5000,
-        total_des_operations_credit: 0,
-        check_debit: 5000,
-        check_credit: 0,
-        operations: vec![Operation {
-            date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-            nature_des_operations: "TEST OPERATION".to_string(),
-            valeur: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-            montant: 5000,
-            montant_type: SoldeType::Debit,
-        }],
-    };
+    let releve = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![debit(date(2024, 1, 5), "TEST OPERATION", 5000)],
+    );
 
     let result = ventilate(spec, &[releve]);
     let err = result.unwrap_err();
@@ -231,48 +161,26 @@ fn test_ventilate_error_on_second_operation() {
         assignments: vec![
             Assignment {
                 name: "Cirque".to_string(),
-                patterns: vec!["CIRQUE".to_string()],
-                ignore: false,
+                patterns: vec!["CIRQUE".into()],
+                ..Default::default()
             },
             Assignment {
                 name: "Soleil".to_string(),
-                patterns: vec!["SOLEIL".to_string()],
-                ignore: false,
+                patterns: vec!["SOLEIL".into()],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     };
 
-    let releve = Releve {
-        date_du_releve: NaiveDate::from_ymd_opt(2024, 1, 13).unwrap(),
-        solde_ouverture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 100000,
-        },
-        solde_cloture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 90000,
-        },
-        total_des_operations_debit: 10000,
-        total_des_operations_credit: 0,
-        check_debit: 10000,
-        check_credit: 0,
-        operations: vec![
-            Operation {
-                date: NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
-                nature_des_operations: "RESTAURANT".to_string(),
-                valeur: NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
-                montant: 5000,
-                montant_type: SoldeType::Debit,
-            },
-            Operation {
-                date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-                nature_des_operations: "CIRQUE DU SOLEIL".to_string(),
-                valeur: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-                montant: 5000,
-                montant_type: SoldeType::Debit,
-            },
+    let releve = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![
+            debit(date(2024, 1, 3), "RESTAURANT", 5000),
+            debit(date(2024, 1, 5), "CIRQUE DU SOLEIL", 5000),
         ],
-    };
+    );
 
     let result = ventilate(spec, &[releve]);
     assert!(result.is_err());
@@ -290,61 +198,27 @@ fn test_ventilate_not_assigned() {
         name: "Test".to_string(),
         assignments: vec![Assignment {
             name: "Cirque".to_string(),
-            patterns: vec!["CIRQUE".to_string()],
-            ignore: false,
+            patterns: vec!["CIRQUE".into()],
+            ..Default::default()
         }],
+        ..Default::default()
     };
 
-    let releve = Releve {
-        date_du_releve: NaiveDate::from_ymd_opt(2024, 1, 13).unwrap(),
-        solde_ouverture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 100000,
-        },
-        solde_cloture: Solde {
-            solde_type: SoldeType::Credit,
-            montant: 80000,
-        },
-        total_des_operations_debit: 20000,
-        total_des_operations_credit: 0,
-        check_debit: 20000,
-        check_credit: 0,
-        operations: vec![
-            Operation {
-                date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-                nature_des_operations: "CIRQUE DU SOLEIL".to_string(),
-                valeur: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-                montant: 5000,
-                montant_type: SoldeType::Debit,
-            },
-            Operation {
-                date: NaiveDate::from_ymd_opt(2024, 1, 8).unwrap(),
-                nature_des_operations: "RESTAURANT".to_string(),
-                valeur: NaiveDate::from_ymd_opt(2024, 1, 8).unwrap(),
-                montant: 3000,
-                montant_type: SoldeType::Debit,
-            },
-            Operation {
-                date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
-                nature_des_operations: "SUPERMARCHE".to_string(),
-                valeur: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
-                montant: 12000,
-                montant_type: SoldeType::Debit,
-            },
-            Operation {
-                date: NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(),
-                nature_des_operations: "SALAIRE".to_string(),
-                valeur: NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(),
-                montant: 200000,
-                montant_type: SoldeType::Credit,
-            },
+    let releve = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![
+            debit(date(2024, 1, 5), "CIRQUE DU SOLEIL", 5000),
+            debit(date(2024, 1, 8), "RESTAURANT", 3000),
+            debit(date(2024, 1, 10), "SUPERMARCHE", 12000),
+            credit(date(2024, 1, 12), "SALAIRE", 200000),
         ],
-    };
+    );
 
     let result = ventilate(spec, &[releve]).unwrap();
 
-    assert_eq!(result.ventilation.get("Cirque"), Some(&5000));
-    assert_eq!(result.not_assigned, 15000); // 3000 + 12000 (RESTAURANT + SUPERMARCHE)
+    assert_eq!(result.debit.ventilation.get("Cirque"), Some(&5000));
+    assert_eq!(result.debit.not_assigned, 15000); // 3000 + 12000 (RESTAURANT + SUPERMARCHE)
 }
 
 #[test]
@@ -353,13 +227,14 @@ fn test_ventilate_sum_mismatch() {
         name: "Test".to_string(),
         assignments: vec![Assignment {
             name: "Cirque".to_string(),
-            patterns: vec!["CIRQUE".to_string()],
-            ignore: false,
+            patterns: vec!["CIRQUE".into()],
+            ..Default::default()
         }],
+        ..Default::default()
     };
 
     let releve = Releve {
-        date_du_releve: NaiveDate::from_ymd_opt(2024, 1, 13).unwrap(),
+        date_du_releve: date(2024, 1, 13),
         solde_ouverture: Solde {
             solde_type: SoldeType::Credit,
             montant: 100000,
@@ -372,22 +247,723 @@ fn test_ventilate_sum_mismatch() {
         total_des_operations_credit: 0,
         check_debit: 10000,
         check_credit: 0,
-        operations: vec![Operation {
-            date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-            nature_des_operations: "CIRQUE DU SOLEIL".to_string(),
-            valeur: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
-            montant: 5000,
-            montant_type: SoldeType::Debit,
-        }],
+        operations: vec![debit(date(2024, 1, 5), "CIRQUE DU SOLEIL", 5000)],
+        account: None,
     };
 
     let result = ventilate(spec, &[releve]);
     assert!(result.is_err());
     match result.unwrap_err() {
-        VentilateError::SumMismatch { expected, actual } => {
+        VentilateError::SumMismatch {
+            expected, actual, ..
+        } => {
             assert_eq!(expected, 10000);
             assert_eq!(actual, 5000);
         }
         _ => panic!("Expected SumMismatch error"),
     }
 }
+
+#[test]
+fn test_ventilate_credits() {
+    let spec = VentilationSpec {
+        name: "Test".to_string(),
+        assignments: vec![Assignment {
+            name: "Cirque".to_string(),
+            patterns: vec!["CIRQUE".into()],
+            ..Default::default()
+        }],
+        income: vec![Assignment {
+            name: "Salaire".to_string(),
+            patterns: vec!["SALAIRE".into()],
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+
+    let releve = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![
+            debit(date(2024, 1, 5), "CIRQUE DU SOLEIL", 5000),
+            credit(date(2024, 1, 10), "VIR SEPA RECU SALAIRE", 200000),
+            credit(date(2024, 1, 11), "REMBOURSEMENT CIRQUE", 2000),
+        ],
+    );
+
+    let result = ventilate(spec.clone(), std::slice::from_ref(&releve)).unwrap();
+
+    // Debit patterns do not apply to credits
+    assert_eq!(result.debit.ventilation.get("Cirque"), Some(&5000));
+    assert_eq!(result.debit.not_assigned, 0);
+    assert_eq!(result.credit.ventilation.get("Salaire"), Some(&200000));
+    assert_eq!(result.credit.ventilation.get("Cirque"), None);
+    assert_eq!(result.credit.not_assigned, 2000);
+    assert_eq!(result.credit.not_assigned_operations.len(), 1);
+
+    let mut wrong_total = releve;
+    wrong_total.total_des_operations_credit = 200000;
+    match ventilate(spec, &[wrong_total]).unwrap_err() {
+        VentilateError::SumMismatch {
+            montant_type,
+            expected,
+            actual,
+        } => {
+            assert_eq!(montant_type, SoldeType::Credit);
+            assert_eq!(expected, 200000);
+            assert_eq!(actual, 202000);
+        }
+        _ => panic!("Expected SumMismatch error"),
+    }
+}
+
+#[test]
+fn test_ventilate_hierarchical_categories() {
+    let assignment = |name: &str, pattern: &str| Assignment {
+        name: name.to_string(),
+        patterns: vec![pattern.into()],
+        ..Default::default()
+    };
+    let spec = VentilationSpec {
+        name: "Test".to_string(),
+        assignments: vec![
+            assignment("Transport/Train", "SNCF"),
+            assignment("Transport/Taxi", "G7"),
+            assignment("Transport", "RATP"),
+            assignment("Loisirs", "CINEMA"),
+        ],
+        ..Default::default()
+    };
+    let operation = |nature: &str, montant: i64| debit(date(2024, 1, 5), nature, montant);
+    let releve = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![
+            operation("SNCF VOYAGEURS", 6000),
+            operation("TAXI G7", 2500),
+            operation("RATP NAVIGO", 1500),
+            operation("CINEMA", 1000),
+        ],
+    );
+
+    let result = ventilate(spec, &[releve]).unwrap();
+
+    assert_eq!(result.debit.ventilation.get("Transport/Train"), Some(&6000));
+    assert_eq!(result.debit.ventilation.get("Transport"), Some(&1500));
+    assert_eq!(result.debit.totals.get("Transport"), Some(&10000));
+    assert_eq!(result.debit.totals.get("Transport/Taxi"), Some(&2500));
+    assert_eq!(result.debit.totals.get("Loisirs"), Some(&1000));
+
+    assert_eq!(
+        category_ancestors("Transport/Train/TGV"),
+        vec!["Transport", "Transport/Train", "Transport/Train/TGV"]
+    );
+    assert_eq!(
+        truncate_category("Transport/Train/TGV", 2),
+        "Transport/Train"
+    );
+    assert_eq!(truncate_category("Loisirs", 2), "Loisirs");
+}
+
+#[test]
+fn test_most_specific_counts_literal_characters() {
+    let specificity = |pattern: &str| Pattern::from(pattern).specificity();
+    assert_eq!(specificity("CARREFOUR"), 9);
+    assert_eq!(specificity(".*.*.*"), 0);
+    assert_eq!(specificity(r"(?i)CB\s+FNAC\.COM"), 10);
+    assert_eq!(specificity("[A-Z]{3} PARIS"), 6);
+
+    // The conditions of a rule do not make it more specific than a longer regex
+    let spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+resolution: most_specific
+assignments:
+- name: Gros achats
+  patterns:
+  - pattern: CB
+    min_amount: 10000
+    weekdays: [Sat, Sun]
+- name: Courses
+  patterns: ['CB CARREFOUR']
+- name: Tout
+  patterns: ['.*.*.*.*.*.*']
+",
+    )
+    .unwrap();
+    let rule = &spec.assignments[0].patterns[0];
+    assert_eq!(rule.specificity(), 2);
+    assert!(rule.to_string().chars().count() > 12);
+    let compiled = CompiledSpec::new(spec).unwrap();
+    // A Saturday
+    let operation = debit(date(2024, 1, 6), "CB CARREFOUR MARKET", 25000);
+    let (assigned, matches) = compiled.assign(&operation, None).unwrap();
+    assert_eq!(matches.len(), 3);
+    assert_eq!(assigned.unwrap().assignment, "Courses");
+}
+
+#[test]
+fn test_ventilate_resolution_modes() {
+    let assignment = |name: &str, pattern: &str, priority: i32| Assignment {
+        name: name.to_string(),
+        patterns: vec![pattern.into()],
+        priority,
+        ..Default::default()
+    };
+    let mut spec = VentilationSpec {
+        name: "Test".to_string(),
+        assignments: vec![
+            assignment("Achats", "AMAZON", 0),
+            assignment("Abonnements", "AMAZON PRIME", 0),
+            assignment("Loisirs", "PRIME", 5),
+        ],
+        ..Default::default()
+    };
+    let operation = |nature: &str, montant: i64| debit(date(2024, 1, 5), nature, montant);
+    let releve = releve(
+        date(2024, 1, 13),
+        100000,
+        vec![
+            operation("AMAZON PRIME FR", 699),
+            operation("AMAZON MKTPLACE", 5200),
+        ],
+    );
+    let releves = [releve];
+    let assigned_to = |result: &my_bank_statements::ventilation::model::Ventilation| {
+        let mut names: Vec<&String> = result.debit.ventilation.keys().collect();
+        names.sort();
+        names.into_iter().cloned().collect::<Vec<_>>()
+    };
+
+    assert!(matches!(
+        ventilate(spec.clone(), &releves),
+        Err(VentilateError::MultipleMatch { .. })
+    ));
+
+    spec.resolution = Resolution::FirstInFile;
+    let result = ventilate(spec.clone(), &releves).unwrap();
+    assert_eq!(result.debit.ventilation.get("Achats"), Some(&5899));
+    assert_eq!(result.debit.ambiguous_matches.len(), 1);
+    let warning = &result.debit.ambiguous_matches[0];
+    assert_eq!(warning.operation, "AMAZON PRIME FR");
+    assert_eq!(warning.chosen, "Achats");
+    assert_eq!(warning.matches.len(), 3);
+
+    spec.resolution = Resolution::MostSpecific;
+    let result = ventilate(spec.clone(), &releves).unwrap();
+    assert_eq!(assigned_to(&result), vec!["Abonnements", "Achats"]);
+    assert_eq!(result.debit.ventilation.get("Abonnements"), Some(&699));
+
+    // Every matching pattern of an assignment counts, not only its first one
+    let mut longer = spec.clone();
+    longer.assignments[0]
+        .patterns
+        .push("AMAZON PRIME FR".into());
+    let compiled = CompiledSpec::new(longer.clone()).unwrap();
+    let (assigned, matches) = compiled.assign(&releves[0].operations[0], None).unwrap();
+    let assigned = assigned.unwrap();
+    assert_eq!(assigned.assignment, "Achats");
+    assert_eq!(assigned.pattern, "AMAZON PRIME FR");
+    assert_eq!(matches[0].pattern, "AMAZON");
+    let result = ventilate(longer, &releves).unwrap();
+    assert_eq!(result.debit.ventilation.get("Achats"), Some(&5899));
+
+    spec.resolution = Resolution::Priority;
+    let result = ventilate(spec.clone(), &releves).unwrap();
+    assert_eq!(result.debit.ventilation.get("Loisirs"), Some(&699));
+    assert_eq!(result.debit.ventilation.get("Achats"), Some(&5200));
+
+    // Resolution is read from the spec file, strict by default
+    let spec: VentilationSpec =
+        serde_yaml::from_str("name: Test\nresolution: most_specific\nassignments: []\n").unwrap();
+    assert_eq!(spec.resolution, Resolution::MostSpecific);
+    let spec: VentilationSpec = serde_yaml::from_str("name: Test\nassignments: []\n").unwrap();
+    assert_eq!(spec.resolution, Resolution::Strict);
+}
+
+#[test]
+fn test_ventilate_rules() {
+    let spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+resolution: first_in_file
+assignments:
+- name: Voyages
+  patterns:
+  - pattern: SNCF
+    min_amount: 10000
+- name: Trajets
+  patterns:
+  - pattern: SNCF
+    max_amount: 9999
+- name: Sorties
+  patterns:
+  - pattern: BAR
+    weekdays: [Fri, Sat]
+    from: 2024-01-01
+    to: 2024-12-31
+- name: Cheques
+  patterns:
+  - pattern: CHEQUE
+    min_valeur_delay: 3
+    account: joint
+- name: Divers
+  patterns:
+  - BAR
+  - CHEQUE
+income:
+- name: Remboursements
+  patterns:
+  - pattern: SNCF
+    montant_type: Credit
+",
+    )
+    .unwrap();
+    assert_eq!(spec.assignments[4].patterns[0], Pattern::from("BAR"));
+
+    let operation = |(m, d): (u32, u32), valeur: u32, nature: &str, montant: i64| Operation {
+        valeur: date(2024, m, valeur),
+        ..debit(date(2024, m, d), nature, montant)
+    };
+    let mut refund = operation((1, 20), 20, "SNCF REMBOURSEMENT", 4000);
+    refund.montant_type = SoldeType::Credit;
+    let releve = in_account(
+        "joint",
+        releve(
+            date(2024, 1, 31),
+            100000,
+            vec![
+                // 2024-01-05 is a Friday, 2024-01-08 a Monday
+                operation((1, 5), 5, "SNCF TGV PARIS LYON", 12000),
+                operation((1, 6), 6, "SNCF TRANSILIEN", 2000),
+                operation((1, 5), 5, "BAR DU COIN", 1500),
+                operation((1, 8), 8, "BAR DU COIN", 500),
+                operation((1, 10), 15, "CHEQUE 1234567", 25000),
+                operation((1, 12), 13, "CHEQUE 1234568", 3000),
+                refund,
+            ],
+        ),
+    );
+
+    let result = ventilate(spec, &[releve]).unwrap();
+
+    assert_eq!(result.debit.ventilation.get("Voyages"), Some(&12000));
+    assert_eq!(result.debit.ventilation.get("Trajets"), Some(&2000));
+    assert_eq!(result.debit.ventilation.get("Sorties"), Some(&1500));
+    assert_eq!(result.debit.ventilation.get("Cheques"), Some(&25000));
+    assert_eq!(result.debit.ventilation.get("Divers"), Some(&3500));
+    assert_eq!(result.credit.ventilation.get("Remboursements"), Some(&4000));
+}
+
+#[test]
+fn test_ventilate_split() {
+    let spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+assignments:
+- name: Courses
+  patterns: [HYPER]
+  split:
+  - name: Maison
+    percent: 50
+- name: Loyer
+  patterns: [LOYER]
+  split:
+  - name: Colocation
+    amount: 40000
+- name: Vacances
+  patterns: [GITE]
+  split:
+  - name: Vacances/Alice
+    percent: 33.34
+  - name: Vacances/Bob
+    percent: 33.33
+  - name: Vacances/Carole
+    percent: 33.33
+",
+    )
+    .unwrap();
+
+    let operation = |nature: &str, montant: i64| debit(date(2024, 1, 5), nature, montant);
+    let releve = releve(
+        date(2024, 1, 31),
+        500000,
+        vec![
+            operation("HYPER U", 10001),
+            operation("LOYER JANVIER", 120000),
+            operation("GITE DE FRANCE", 40000),
+        ],
+    );
+
+    // The sum check still holds with the shares
+    let result = ventilate(spec, &[releve]).unwrap();
+    let debit = &result.debit;
+
+    assert_eq!(debit.ventilation.get("Maison"), Some(&5001));
+    assert_eq!(debit.ventilation.get("Courses"), Some(&5000));
+    assert_eq!(debit.ventilation.get("Colocation"), Some(&40000));
+    assert_eq!(debit.ventilation.get("Loyer"), Some(&80000));
+    assert_eq!(debit.ventilation.get("Vacances/Alice"), Some(&13336));
+    assert_eq!(debit.ventilation.get("Vacances/Bob"), Some(&13332));
+    assert_eq!(debit.ventilation.get("Vacances/Carole"), Some(&13332));
+    assert_eq!(debit.ventilation.get("Vacances"), None);
+    assert_eq!(debit.totals.get("Vacances"), Some(&40000));
+
+    // Each category lists the operation with its share
+    assert_eq!(debit.ventilated_operations["Maison"][0].montant, 5001);
+    assert_eq!(
+        debit.ventilated_operations["Maison"][0].nature_des_operations,
+        "HYPER U"
+    );
+    assert_eq!(debit.ventilated_operations["Courses"][0].montant, 5000);
+}
+
+#[test]
+fn test_invalid_splits() {
+    let spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+assignments:
+- name: Courses
+  patterns: [HYPER]
+  split:
+  - name: Maison
+    percent: 50
+    amount: 1000
+  - name: Jardin
+- name: Vacances
+  patterns: [GITE]
+  split:
+  - name: Vacances/Alice
+    percent: 60
+  - name: Vacances/Bob
+    percent: 60
+income:
+- name: Salaire
+  patterns: [ACME]
+  split:
+  - name: Prime
+    percent: -10
+",
+    )
+    .unwrap();
+
+    let invalid: Vec<String> = spec
+        .invalid_splits()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(
+        invalid,
+        vec![
+            "assignments 'Courses', share 'Maison': both an amount and a percent",
+            "assignments 'Courses', share 'Jardin': neither an amount nor a percent",
+            "assignments 'Vacances', split: percentages add up to 120",
+            "income 'Salaire', share 'Prime': negative percent -10",
+        ]
+    );
+    match CompiledSpec::new(spec) {
+        Err(VentilateError::InvalidSplits(reported)) => assert_eq!(reported.len(), 4),
+        other => panic!("expected InvalidSplits, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_ventilate_overrides() {
+    let mut spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+assignments:
+- name: Courses
+  patterns: [CARREFOUR]
+- name: Cadeaux
+  patterns: [FNAC]
+",
+    )
+    .unwrap();
+
+    let operation = |d: u32, nature: &str, montant: i64| debit(date(2024, 1, d), nature, montant);
+    let gift = operation(20, "CARREFOUR", 8000);
+    let releve = releve(
+        date(2024, 1, 31),
+        100000,
+        vec![
+            operation(5, "CARREFOUR", 4500),
+            gift.clone(),
+            operation(22, "PHARMACIE", 3000),
+        ],
+    );
+
+    let key = operation_key(&gift);
+    assert!(key.starts_with("2024-01-20:-8000:"));
+    assert_eq!(key, operation_key(&gift.clone()));
+    assert_ne!(key, operation_key(&releve.operations[0]));
+    assert!(duplicate_keys(std::slice::from_ref(&releve)).is_empty());
+
+    // A second identical gift on the same day shares the key
+    let mut twice = releve.clone();
+    twice.operations.push(gift.clone());
+    assert_eq!(
+        duplicate_keys(&[twice.clone()])
+            .into_iter()
+            .collect::<Vec<_>>(),
+        vec![key.clone()]
+    );
+    // Operations with an id are told apart by it
+    for (index, op) in twice.operations.iter_mut().enumerate() {
+        op.id = Some(index.to_string());
+    }
+    assert!(duplicate_keys(&[twice]).is_empty());
+
+    spec.overrides = serde_yaml::from_str(&format!(
+        "
+- id: '{key}'
+  assignment: Cadeaux
+- id: '{}'
+  assignment: Sante
+",
+        operation_key(&releve.operations[2])
+    ))
+    .unwrap();
+    let result = ventilate(spec, &[releve]).unwrap();
+
+    assert_eq!(result.debit.ventilation.get("Courses"), Some(&4500));
+    assert_eq!(result.debit.ventilation.get("Cadeaux"), Some(&8000));
+    assert_eq!(result.debit.ventilation.get("Sante"), Some(&3000));
+    assert_eq!(result.debit.not_assigned, 0);
+    assert!(result.debit.ambiguous_matches.is_empty());
+}
+
+#[test]
+fn test_compiled_spec() {
+    let spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+assignments:
+- name: Courses
+  patterns: ['CARREFOUR\\b']
+- name: Gros achats
+  patterns:
+  - min_amount: 50000
+income:
+- name: Salaire
+  patterns: [CARREFOUR]
+",
+    )
+    .unwrap();
+    let compiled = CompiledSpec::new(spec).unwrap();
+
+    let mut operation = debit(date(2024, 1, 5), "CARREFOUR CITY", 4500);
+    let names = |matches: Vec<my_bank_statements::ventilation::ventilate::MatchInfo>| {
+        matches
+            .into_iter()
+            .map(|m| m.assignment)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        names(compiled.find_matches(&operation, None)),
+        vec!["Courses"]
+    );
+    operation.montant = 60000;
+    assert_eq!(
+        names(compiled.find_matches(&operation, None)),
+        vec!["Courses", "Gros achats"]
+    );
+    // Where the compiled regexes match, the whole description without a regex
+    let debit_side = SoldeType::Debit;
+    assert_eq!(
+        compiled.find_span(&debit_side, 0, 0, "CB CARREFOUR CITY"),
+        Some((3, 12))
+    );
+    assert_eq!(compiled.find_span(&debit_side, 0, 0, "CARREFOURS"), None);
+    assert_eq!(compiled.find_span(&debit_side, 1, 0, "FNAC"), Some((0, 4)));
+    assert_eq!(compiled.find_span(&debit_side, 2, 0, "FNAC"), None);
+
+    // Credits are matched against the income assignments
+    operation.montant_type = SoldeType::Credit;
+    assert_eq!(
+        names(compiled.find_matches(&operation, None)),
+        vec!["Salaire"]
+    );
+}
+
+#[test]
+fn test_invalid_patterns() {
+    let spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+assignments:
+- name: Courses
+  patterns: ['CARREFOUR\\b', '(invalid']
+- name: Gros achats
+  patterns:
+  - min_amount: 50000
+  - pattern: '[A-Z'
+income:
+- name: Salaire
+  patterns: [SALAIRE, '*']
+",
+    )
+    .unwrap();
+
+    let invalid = spec.invalid_patterns();
+    let found: Vec<(SoldeType, &str, usize, &str)> = invalid
+        .iter()
+        .map(|p| {
+            (
+                p.pattern.montant_type.clone(),
+                p.pattern.assignment.as_str(),
+                p.pattern.position,
+                p.pattern.pattern.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (SoldeType::Debit, "Courses", 2, "(invalid"),
+            (SoldeType::Debit, "Gros achats", 2, "[A-Z"),
+            (SoldeType::Credit, "Salaire", 2, "*"),
+        ]
+    );
+
+    // Compiling or ventilating with the spec reports all of them
+    match CompiledSpec::new(spec.clone()) {
+        Err(VentilateError::InvalidPatterns(reported)) => assert_eq!(reported, invalid),
+        other => panic!("expected InvalidPatterns, got {other:?}"),
+    }
+    let err = ventilate(spec, &[]).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("assignments 'Courses', pattern 2: '(invalid'"));
+    assert!(message.contains("income 'Salaire', pattern 2: '*'"));
+}
+
+#[test]
+fn test_ventilate_order() {
+    let mut spec: VentilationSpec = serde_yaml::from_str(
+        "
+name: Test
+assignments:
+- name: Transport/Train
+  patterns: [SNCF]
+- name: Loisirs
+  patterns: [CINEMA]
+- name: Courses
+  patterns: [HYPER U]
+  split:
+  - name: Maison
+    percent: 50
+- name: Transport/Taxi
+  patterns: [G7]
+",
+    )
+    .unwrap();
+    let operation = |nature: &str, montant: i64| debit(date(2024, 1, 5), nature, montant);
+    let releve = releve(
+        date(2024, 1, 31),
+        100000,
+        vec![
+            operation("TAXI G7", 2500),
+            operation("HYPER U", 4000),
+            operation("CINEMA", 1000),
+            operation("SNCF VOYAGEURS", 4500),
+        ],
+    );
+    let keys = |map: &indexmap::IndexMap<String, i64>| map.keys().cloned().collect::<Vec<_>>();
+
+    let result = ventilate(spec.clone(), std::slice::from_ref(&releve)).unwrap();
+    assert_eq!(
+        keys(&result.debit.ventilation),
+        vec![
+            "Transport/Train",
+            "Loisirs",
+            "Courses",
+            "Maison",
+            "Transport/Taxi"
+        ]
+    );
+    assert_eq!(
+        result
+            .debit
+            .ventilated_operations
+            .keys()
+            .collect::<Vec<_>>(),
+        vec![
+            "Transport/Train",
+            "Loisirs",
+            "Courses",
+            "Maison",
+            "Transport/Taxi"
+        ]
+    );
+    // Each category comes before its sub-categories
+    assert_eq!(
+        keys(&result.debit.totals),
+        vec![
+            "Transport",
+            "Transport/Train",
+            "Transport/Taxi",
+            "Loisirs",
+            "Courses",
+            "Maison"
+        ]
+    );
+    // Successive runs give the same YAML
+    let yaml = serde_yaml::to_string(&result).unwrap();
+    let again = ventilate(spec.clone(), std::slice::from_ref(&releve)).unwrap();
+    assert_eq!(serde_yaml::to_string(&again).unwrap(), yaml);
+
+    spec.order = Order::Sorted;
+    let result = ventilate(spec, &[releve]).unwrap();
+    assert_eq!(
+        keys(&result.debit.ventilation),
+        vec![
+            "Courses",
+            "Loisirs",
+            "Maison",
+            "Transport/Taxi",
+            "Transport/Train"
+        ]
+    );
+    assert_eq!(
+        keys(&result.debit.totals),
+        vec![
+            "Courses",
+            "Loisirs",
+            "Maison",
+            "Transport",
+            "Transport/Taxi",
+            "Transport/Train"
+        ]
+    );
+}
+
+#[test]
+fn test_regex_set_size_limit() {
+    // Each regex compiles on its own, the set of them is too large
+    let patterns: Vec<String> = (0..3)
+        .map(|i| format!("- '(?:[a-z]{{100}}){{1000}}{i}'"))
+        .collect();
+    let spec: VentilationSpec = serde_yaml::from_str(&format!(
+        "
+name: Test
+assignments:
+- name: Courses
+  patterns:
+  {}
+",
+        patterns.join("\n  ")
+    ))
+    .unwrap();
+    assert!(spec.validate().is_ok());
+    match CompiledSpec::new(spec) {
+        Err(err @ VentilateError::RegexSet { .. }) => {
+            assert!(
+                err.to_string()
+                    .starts_with("The patterns of the assignments cannot be compiled together")
+            )
+        }
+        other => panic!("expected RegexSet, got {other:?}"),
+    }
+}